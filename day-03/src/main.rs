@@ -2,95 +2,185 @@ use std::fs::File;
 use std::io::{self, BufReader, BufRead};
 use std::env;
 
-use std::collections::HashSet;
-use std::collections::HashMap;
-use std::iter::FromIterator;
-use lazy_static::lazy_static;
-
-lazy_static! {
-    static ref HASHMAP: HashMap<char, u64> = {
-        let mut m : HashMap<char, u64> = HashMap::new();
-        ('a'..='z').into_iter().enumerate().for_each(|(i,c)|{
-            m.insert(c, (i + 1) as u64);
-        });
-        ('A'..='Z').into_iter().enumerate().for_each(|(i,c)|{
-            m.insert(c, (i + 27) as u64);
-        });
-        m
-    };
-}
-
 fn main() ->  io::Result<()> {
     let args: Vec<String> = env::args().collect();
     let input = &args[1];
 
+    let mut group_size = 3;
+    let mut compartments = 2;
+    let mut report = false;
+    let mut options = args.iter().skip(2);
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--group-size" => group_size = parse_count(option, options.next())?,
+            "--compartments" => compartments = parse_count(option, options.next())?,
+            "--report" => report = true,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                           format!("unknown option {}", option))),
+        }
+    }
+
+    let rucksacks = read_input(input, compartments)?;
+    let groups = groups(&rucksacks, group_size)?;
+
+    if report {
+        print_report(&rucksacks, &groups);
+    }
+
     println!("{:?} is the sum of the priorities of those item types",
-             read_input(input)?.iter()
-                 .map(|x|x.intersection().iter().map(|c|priority(c)).sum::<u64>())
+             rucksacks.iter()
+                 .map(|x|priority_sum(&x.duplicates()))
                  .sum::<u64>());
 
     println!("{:?} is the sum of the priorities of those item types",
-             read_input_part2(input)?.iter()
-                 .map(|x|x.common_items().iter().map(|c|priority(c)).sum::<u64>())
+             groups.iter()
+                 .map(|x|priority_sum(&x.badges()))
                  .sum::<u64>());
     Ok(())
 }
 
-fn read_input(filename: &String) ->  io::Result<Vec<Rucksack>> {
-    let file_in = File::open(filename)?;
-    let file_reader = BufReader::new(file_in).lines();
-    Ok(file_reader.map(|x| x.unwrap())
-                  .map(|x|Rucksack::new(x))
-                  .collect())
+fn parse_count(option: &str, value: Option<&String>) -> io::Result<usize> {
+    value.and_then(|x| x.parse::<usize>().ok())
+         .filter(|x| *x > 0)
+         .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+                                       format!("{} expects a positive number", option)))
 }
 
-fn read_input_part2(filename: &String) ->  io::Result<Vec<Group>> {
+fn read_input(filename: &String, compartments: usize) ->  io::Result<Vec<Rucksack>> {
     let file_in = File::open(filename)?;
     let file_reader = BufReader::new(file_in).lines();
-    Ok(file_reader.map(|x| x.unwrap())
-        .map(|x|Rucksack::new(x))
-        .collect::<Vec<Rucksack>>()
-        .chunks(3)
-        .map(|x| x.into_iter()
-                             .map(|x|x.clone())
-                             .collect::<Vec<Rucksack>>())
-        .map(|x|Group::new(x))
-        .collect::<Vec<Group>>()
-     )
+    file_reader.enumerate()
+               .map(|(i, x)| {
+                   let line = x?;
+                   Rucksack::new(&line, compartments).map_err(|e| {
+                       io::Error::new(io::ErrorKind::InvalidData,
+                                      format!("line {}: {}", i + 1, e))
+                   })
+               })
+               .collect()
+}
+
+fn print_report(rucksacks: &[Rucksack], groups: &[Group]) {
+    rucksacks.iter().enumerate().for_each(|(i, x)| {
+        println!("rucksack {}: {}", i + 1, describe(&x.duplicates()));
+    });
+    groups.iter().enumerate().for_each(|(i, x)| {
+        let badges = x.badges();
+        let warning = match badges.len() {
+            0 => " (no shared badge)",
+            1 => "",
+            _ => " (several shared badges)",
+        };
+        println!("group {}: {}{}", i + 1, describe(&badges), warning);
+    });
+}
+
+fn describe(items: &[(char, u64)]) -> String {
+    if items.is_empty() {
+        return String::from("none");
+    }
+    items.iter()
+         .map(|(c, priority)| format!("{} (priority {})", c, priority))
+         .collect::<Vec<String>>()
+         .join(", ")
+}
+
+// Rucksack i comes from line i + 1, every group needs exactly `group_size` of them.
+fn groups(rucksacks: &[Rucksack], group_size: usize) -> io::Result<Vec<Group>> {
+    let incomplete = rucksacks.len() % group_size;
+    if incomplete != 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                  format!("line {}: the last group has only {} of {} rucksacks",
+                                          rucksacks.len() - incomplete + 1, incomplete, group_size)));
+    }
+    Ok(rucksacks.chunks(group_size)
+                .map(|x| Group::new(x.to_vec()))
+                .collect())
+}
+
+fn priority(c: char) -> Option<u64> {
+    match c {
+        'a'..='z' => Some(c as u64 - 'a' as u64 + 1),
+        'A'..='Z' => Some(c as u64 - 'A' as u64 + 27),
+        _ => None,
+    }
 }
 
-fn priority(c: &char) -> &u64 {
-    HASHMAP.get(c).unwrap()
+fn item(priority: u64) -> char {
+    if priority <= 26 {
+        (b'a' + (priority - 1) as u8) as char
+    } else {
+        (b'A' + (priority - 27) as u8) as char
+    }
+}
+
+fn priority_sum(items: &[(char, u64)]) -> u64 {
+    items.iter().map(|(_, priority)| priority).sum()
+}
+
+// Bit n - 1 is set when the item with priority n is present.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+struct ItemSet(u64);
+
+impl ItemSet {
+    fn from_items(items: &str) -> Self {
+        ItemSet(items.chars()
+                     .filter_map(priority)
+                     .fold(0, |mask, priority| mask | 1 << (priority - 1)))
+    }
+
+    fn intersection(self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 & other.0)
+    }
+
+    fn union(self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 | other.0)
+    }
+
+    fn items(self) -> Vec<(char, u64)> {
+        (1..=52).filter(|priority| self.0 & 1 << (priority - 1) != 0)
+                .map(|priority| (item(priority), priority))
+                .collect()
+    }
+}
+
+fn intersect_all<I: IntoIterator<Item = ItemSet>>(sets: I) -> ItemSet {
+    sets.into_iter().reduce(ItemSet::intersection).unwrap_or_default()
+}
+
+fn union_all<I: IntoIterator<Item = ItemSet>>(sets: I) -> ItemSet {
+    sets.into_iter().reduce(ItemSet::union).unwrap_or_default()
 }
 
 #[derive(Clone)]
 struct Rucksack {
-    left: Vec<char>,
-    right: Vec<char>
+    compartments: Vec<ItemSet>,
 }
 
 impl Rucksack {
-    fn new(input: String) -> Self {
-        let left: Vec<char> = input[0..input.len() / 2].chars().collect();
-        let right: Vec<char> = input[input.len() / 2..input.len()].chars().collect();
-        Rucksack {
-            left,
-            right,
+    fn new(input: &str, compartments: usize) -> Result<Self, String> {
+        let items: Vec<char> = input.chars().collect();
+        if let Some((column, c)) = items.iter().enumerate().find(|(_, c)| priority(**c).is_none()) {
+            return Err(format!("unknown item {:?} at column {}", c, column + 1));
+        }
+        if items.is_empty() || !items.len().is_multiple_of(compartments) {
+            return Err(format!("{} items cannot be split into {} equal compartments",
+                               items.len(), compartments));
         }
+        let size = items.len() / compartments;
+        Ok(Rucksack {
+            compartments: items.chunks(size)
+                               .map(|x| ItemSet::from_items(&x.iter().collect::<String>()))
+                               .collect(),
+        })
     }
 
-    fn intersection(&self) -> Vec<char> {
-        let left : HashSet<char> = HashSet::from_iter(self.left.clone().into_iter());
-        let right: HashSet<char>  = HashSet::from_iter(self.right.clone().into_iter());
-        let intersection: Vec<char> = left.intersection(&right).map(|x|x.clone()).collect();
-        intersection
+    fn duplicates(&self) -> Vec<(char, u64)> {
+        intersect_all(self.compartments.iter().copied()).items()
     }
 
-    fn union(&self) -> HashSet<char> {
-        let mut rvalue: Vec<char> = Vec::new();
-        rvalue.extend(&self.left);
-        rvalue.extend(&self.right);
-        HashSet::from_iter(rvalue.into_iter())
+    fn union(&self) -> ItemSet {
+        union_all(self.compartments.iter().copied())
     }
 }
 
@@ -105,11 +195,11 @@ impl Group {
         }
     }
 
-    fn common_items(&self) -> HashSet<char> {
-        // TODO for next year find elegant way to loop through sets
-        let mut start = self.rucksacks.get(0).unwrap().union();
-        start = start.intersection(&self.rucksacks.get(1).unwrap().union()).into_iter().map(|x| x.clone()).collect::<HashSet<char>>();
-        start = start.intersection(&self.rucksacks.get(2).unwrap().union()).into_iter().map(|x| x.clone()).collect::<HashSet<char>>();
-        start
+    fn common_items(&self) -> ItemSet {
+        intersect_all(self.rucksacks.iter().map(|x| x.union()))
     }
-}
\ No newline at end of file
+
+    fn badges(&self) -> Vec<(char, u64)> {
+        self.common_items().items()
+    }
+}
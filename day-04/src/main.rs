@@ -1,76 +1,169 @@
+use std::collections::BTreeSet;
 use std::fs::File;
 use std::io::{self, BufReader, BufRead};
 use std::env;
 use std::ops::Range;
+use interval_set::IntervalSet;
 use lazy_static::lazy_static;
 use regex::Regex;
 
 lazy_static! {
-        static ref RE: Regex = Regex::new(r"^(\d+)-(\d+),(\d+)-(\d+)$").unwrap();
+        static ref RE: Regex = Regex::new(r"^(\d+)-(\d+)$").unwrap();
 }
 
 fn main() ->  io::Result<()> {
     let args: Vec<String> = env::args().collect();
     let input = &args[1];
+    let sweep = args.iter().skip(2).any(|x| x == "--sweep");
 
     let input = read_input(input)?;
 
     println!("{:?} is how many assignment pairs does one range fully contain the other",
              input.iter()
                  .filter(|x|x.one_contains_the_other())
-                 .fold(0, |sum, _val| sum + 1));
+                 .count());
 
     println!("{:?} is in how many assignment pairs do the ranges overlap",
         input.iter()
                  .filter(|x|x.one_has_some_intersection_with_other())
-                 .fold(0, |sum, _val| sum + 1));
+                 .count());
+
+    if sweep {
+        print_sweep(&SweepAnalysis::new(&input));
+    }
     Ok(())
 }
 
-fn read_input(filename: &String) ->  io::Result<Vec<RangePair>> {
+fn read_input(filename: &String) ->  io::Result<Vec<AssignmentGroup>> {
     let file_in = File::open(filename)?;
     let file_reader = BufReader::new(file_in).lines();
-    Ok(file_reader.map(|x|x.unwrap())
-                  .map(|x| string_2_range_pair(x))
-                  .collect::<Vec<RangePair>>())
+    file_reader.map(|x| x.map(|x| string_2_assignment_group(&x)))
+               .collect()
 }
 
-fn string_2_range_pair(s: String) -> RangePair {
-    let mut cap = RE.captures_iter(s.trim());
-    let cap  = cap.next().unwrap();
-    let lb_l = cap[1].parse::<i32>().unwrap();
-    let ub_l = cap[2].parse::<i32>().unwrap() + 1;
-    let lb_r = cap[3].parse::<i32>().unwrap();
-    let ub_r = cap[4].parse::<i32>().unwrap() + 1;
+fn string_2_assignment_group(s: &str) -> AssignmentGroup {
+    AssignmentGroup::new(s.trim()
+                          .split(',')
+                          .map(|x| {
+                              let cap = RE.captures(x.trim()).unwrap();
+                              let lb = cap[1].parse::<i64>().unwrap();
+                              let ub = cap[2].parse::<i64>().unwrap() + 1;
+                              lb..ub
+                          })
+                          .collect())
+}
+
+fn print_sweep(analysis: &SweepAnalysis) {
+    println!("{:?} is the maximum number of elves covering a single section",
+             analysis.max_coverage);
+
+    println!("{} are the sections covered by nobody",
+             if analysis.uncovered.is_empty() {
+                 String::from("none")
+             } else {
+                 analysis.uncovered.iter()
+                     .map(format_range)
+                     .collect::<Vec<String>>()
+                     .join(", ")
+             });
 
-    RangePair::new(lb_l..ub_l, lb_r..ub_r)
+    for (elf, (range, overlapping)) in analysis.elves.iter()
+                                               .zip(analysis.overlapping.iter())
+                                               .enumerate() {
+        println!("elf {} ({}) overlaps {}",
+                 elf + 1,
+                 format_range(range),
+                 if overlapping.is_empty() {
+                     String::from("no other elf")
+                 } else {
+                     overlapping.iter()
+                         .map(|x| format!("{}", x + 1))
+                         .collect::<Vec<String>>()
+                         .join(", ")
+                 });
+    }
+}
+
+fn format_range(range: &Range<i64>) -> String {
+    format!("{}-{}", range.start, range.end - 1)
 }
 
-struct RangePair {
-    left: Range<i32>,
-    right: Range<i32>,
+struct AssignmentGroup {
+    ranges: Vec<Range<i64>>,
+    sections: Vec<IntervalSet<i64>>,
 }
 
-impl RangePair {
-    fn new(left: Range<i32>, right: Range<i32>)  -> RangePair {
-        RangePair {
-            left,
-            right
+impl AssignmentGroup {
+    fn new(ranges: Vec<Range<i64>>)  -> AssignmentGroup {
+        AssignmentGroup {
+            sections: ranges.iter().map(|x| IntervalSet::from_range(x.clone())).collect(),
+            ranges,
         }
     }
 
+    fn pairs(&self) -> impl Iterator<Item = (&IntervalSet<i64>, &IntervalSet<i64>)> {
+        self.sections.iter()
+            .enumerate()
+            .flat_map(move |(i, l)| self.sections.iter().skip(i + 1).map(move |r| (l, r)))
+    }
+
     fn one_contains_the_other(&self) -> bool {
-        (self.left.contains(&self.right.start)
-            && self.left.contains(&(&self.right.end - 1)))
-        || (self.right.contains(&self.left.start)
-            && self.right.contains(&(&self.left.end - 1)))
+        self.pairs().any(|(left, right)| {
+            left.difference(right).is_empty()
+            || right.difference(left).is_empty()
+        })
     }
 
     fn one_has_some_intersection_with_other(&self) -> bool {
-        self.left.contains(&self.right.start)
-        || self.left.contains(&(&self.right.end - 1))
-        || self.right.contains(&self.left.start)
-        || self.right.contains(&(&self.left.end - 1))
+        self.pairs().any(|(left, right)| !left.intersection(right).is_empty())
     }
 
 }
+
+// Looks at the assignments of every elf at once instead of group by group.
+struct SweepAnalysis {
+    elves: Vec<Range<i64>>,
+    max_coverage: usize,
+    uncovered: Vec<Range<i64>>,
+    overlapping: Vec<BTreeSet<usize>>,
+}
+
+impl SweepAnalysis {
+    fn new(groups: &[AssignmentGroup]) -> Self {
+        let elves: Vec<Range<i64>> = groups.iter()
+                                           .flat_map(|x| x.ranges.iter().cloned())
+                                           .collect();
+
+        // Ranges are half-open, so at equal positions ends are handled before starts.
+        let mut events: Vec<(i64, bool, usize)> =
+            elves.iter()
+                 .enumerate()
+                 .flat_map(|(elf, range)| [(range.start, true, elf), (range.end, false, elf)])
+                 .collect();
+        events.sort();
+
+        let mut overlapping: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); elves.len()];
+        let mut active: BTreeSet<usize> = BTreeSet::new();
+        let mut max_coverage = 0;
+        for (_, is_start, elf) in events {
+            if is_start {
+                for other in active.iter() {
+                    overlapping[*other].insert(elf);
+                    overlapping[elf].insert(*other);
+                }
+                active.insert(elf);
+                max_coverage = max_coverage.max(active.len());
+            } else {
+                active.remove(&elf);
+            }
+        }
+
+        let covered: IntervalSet<i64> = elves.iter().cloned().collect();
+        SweepAnalysis {
+            uncovered: covered.gaps().collect(),
+            elves,
+            max_coverage,
+            overlapping,
+        }
+    }
+}
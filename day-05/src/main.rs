@@ -1,5 +1,5 @@
 use std::fs::File;
-use std::io::{self, BufReader, BufRead};
+use std::io::{self, BufReader, BufRead, Write};
 use std::env;
 use lazy_static::lazy_static;
 use regex::Regex;
@@ -12,69 +12,218 @@ fn main() ->  io::Result<()> {
     let args: Vec<String> = env::args().collect();
     let input = &args[1];
 
+    let mut cranes = vec!["9000", "9001"];
+    let mut replay = ReplayMode::Off;
+    let mut output: Option<&String> = None;
+    let mut options = args.iter().skip(2);
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--crane" => cranes = vec![option_value(option, options.next())?.as_str()],
+            "--replay" => replay = ReplayMode::EveryStep,
+            "--at" => replay = ReplayMode::AtStep(option_value(option, options.next())?
+                                                      .parse::<usize>()
+                                                      .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?),
+            "--interactive" => replay = ReplayMode::Interactive,
+            "--output" => output = Some(option_value(option, options.next())?),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                           format!("unknown option {}", option))),
+        }
+    }
+
     let puzzle = read_input(input)?;
-    println!("After the rearrangement procedure completes the crates {:?} end up on top of each stack",
-             puzzle.clone().solve1());
 
-    println!("After the rearrangement procedure completes the crates {:?} end up on top of each stack",
-             puzzle.clone().solve2());
+    if replay != ReplayMode::Off || output.is_some() {
+        let mut replay_state = Replay::new(&puzzle, crane_from_name(cranes[0])?);
+        match replay {
+            ReplayMode::EveryStep => {
+                println!("{}", render(&replay_state.stacks));
+                while replay_state.step_forward()? {
+                    println!("{}", replay_state.describe());
+                    println!("{}", render(&replay_state.stacks));
+                }
+            },
+            ReplayMode::AtStep(step) => {
+                replay_state.go_to(step)?;
+                println!("{}", replay_state.describe());
+                println!("{}", render(&replay_state.stacks));
+            },
+            ReplayMode::Interactive => run_interactive(&mut replay_state)?,
+            ReplayMode::Off => {},
+        }
+        if let Some(output) = output {
+            replay_state.go_to(puzzle.instructions.len())?;
+            let mut file_out = File::create(output)?;
+            writeln!(file_out, "{}", render(&replay_state.stacks))?;
+        }
+        return Ok(());
+    }
+
+    for name in cranes {
+        let mut crane = crane_from_name(name)?;
+        println!("After the rearrangement procedure completes the crates {:?} end up on top of each stack",
+                 puzzle.solve(crane.as_mut())?);
+        if let Some(report) = crane.report() {
+            println!("{}", report);
+        }
+    }
 
     Ok(())
 }
 
+fn option_value<'a>(option: &str, value: Option<&'a String>) -> io::Result<&'a String> {
+    value.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+                                       format!("{} expects a value", option)))
+}
+
+// Reads commands from stdin: n(ext), b(ack), g(oto) K and q(uit).
+fn run_interactive(replay: &mut Replay) -> io::Result<()> {
+    println!("{}", replay.describe());
+    println!("{}", render(&replay.stacks));
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let mut words = line.split_whitespace();
+        let moved = match (words.next(), words.next()) {
+            (Some("n"), None) => replay.step_forward(),
+            (Some("b"), None) => Ok(replay.step_back()),
+            (Some("g"), Some(step)) => match step.parse::<usize>() {
+                Ok(step) => replay.go_to(step).map(|_| true),
+                Err(e) => Err(io::Error::new(io::ErrorKind::InvalidInput, e)),
+            },
+            (Some("q"), None) => return Ok(()),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                    "expected n, b, g K or q")),
+        };
+        match moved {
+            Ok(true) => {
+                println!("{}", replay.describe());
+                println!("{}", render(&replay.stacks));
+            },
+            Ok(false) => println!("Nothing to do at step {:?}", replay.position),
+            Err(e) => println!("{}", e),
+        }
+    }
+    Ok(())
+}
+
 fn read_input(filename: &String) ->  io::Result<Puzzle> {
     let file_in = File::open(filename)?;
     let lines = BufReader::new(file_in).lines();
     let mut reading_state: ReadingState = ReadingState::ReadingStacks;
 
-    let mut raw_stacks: Vec<Vec<char>> = Vec::new();
+    let mut drawing: Vec<(usize, String)> = Vec::new();
+    let mut stacks: Vec<Stack> = Vec::new();
     let mut instructions: Vec<Instruction> = Vec::new();
-    let mut label_line: String = String::from("");
 
-    for line in lines {
-        let line = line.unwrap();
+    for (line_number, line) in lines.enumerate() {
+        let line_number = line_number + 1;
+        let line = line?;
         match reading_state {
             ReadingState::ReadingStacks => {
                 if contains_crate(line.as_str()) {
-                    raw_stacks.push(line.chars().collect::<Vec<char>>())
+                    drawing.push((line_number, line));
                 } else {
-                    label_line = line;
+                    stacks = parse_drawing(&drawing, line_number, line.as_str())?;
                     reading_state = ReadingState::ExpectingEmptyLine;
                 }
             },
             ReadingState::ExpectingEmptyLine => {
+                if !line.trim().is_empty() {
+                    return Err(input_error(line_number, "expected an empty line after the stack labels"));
+                }
                 reading_state = ReadingState::ReadingInstructions;
             },
             ReadingState::ReadingInstructions=> {
-                let mut cap = INSTRUCTION_RE.captures_iter(line.trim());
-                let cap  = cap.next().unwrap();
-                let amount = cap[1].parse::<usize>().unwrap();
-                let from = cap[2].parse::<usize>().unwrap();
-                let to = cap[3].parse::<usize>().unwrap() ;
-                instructions.push(Instruction::new(amount, from, to))
+                if !line.trim().is_empty() {
+                    instructions.push(parse_instruction(line_number, line.as_str(), stacks.len())?);
+                }
             }
         }
     }
-    let amount_of_crates: u32 = label_line.chars().last().unwrap().to_digit(10).unwrap();
 
-    let first_crate_index: usize = 1;
+    if let ReadingState::ReadingStacks = reading_state {
+        return Err(input_error(drawing.len(), "the drawing has no stack labels"));
+    }
+
+    Ok(Puzzle::new(stacks,
+                   instructions))
+}
+
+fn input_error(line_number: usize, message: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line_number, message))
+}
+
+// Each label owns the columns it is printed in; a crate belongs to the label whose columns
+// overlap its brackets, so labels of any width and rows of any length line up.
+fn parse_drawing(drawing: &[(usize, String)],
+                 label_line_number: usize,
+                 label_line: &str) -> io::Result<Vec<Stack>> {
+    let labels = tokens(label_line);
+    if labels.is_empty() {
+        return Err(input_error(label_line_number, "expected stack labels"));
+    }
+    for (i, (_, label)) in labels.iter().enumerate() {
+        if label.parse::<usize>().ok() != Some(i + 1) {
+            return Err(input_error(label_line_number,
+                                   format!("expected stack label {} but found {:?}", i + 1, label)));
+        }
+    }
 
-    let mut my_stacks: Vec<Stack> = Vec::new();
-    (0..amount_of_crates).for_each(|_| my_stacks.push( Stack::new() ));
-    (0..raw_stacks.len()).rev().for_each(|y| {
-        (0..amount_of_crates).map(|x| x as usize).for_each(|x| {
-            let stack = raw_stacks.get(y).unwrap();
-            let effective_index = first_crate_index + ("] [ ".len() * x);
-            let c = stack.get(effective_index).unwrap_or(&' ');
-            if c != &' ' {
-                my_stacks.get_mut(x).unwrap().push_crate(*c);
+    let mut stacks: Vec<Stack> = labels.iter().map(|_| Stack::new()).collect();
+    for (line_number, line) in drawing.iter().rev() {
+        for (start, token) in tokens(line.as_str()) {
+            let chars: Vec<char> = token.chars().collect();
+            if chars.len() != 3 || chars[0] != '[' || chars[2] != ']' {
+                return Err(input_error(*line_number,
+                                       format!("expected a crate like [A] but found {:?}", token)));
             }
+            let end = start + chars.len();
+            let stack = labels.iter()
+                              .position(|(label_start, label)| {
+                                  *label_start < end && start < label_start + label.len()
+                              })
+                              .ok_or_else(|| input_error(*line_number,
+                                                         format!("crate {} at column {} is not above a stack label",
+                                                                 token, start + 1)))?;
+            stacks[stack].push_crate(chars[1]);
+        }
+    }
+    Ok(stacks)
+}
 
-        })
-    });
+fn tokens(line: &str) -> Vec<(usize, String)> {
+    let mut tokens: Vec<(usize, String)> = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+    for (column, c) in line.chars().enumerate() {
+        if c.is_whitespace() {
+            if let Some(token) = current.take() {
+                tokens.push(token);
+            }
+        } else {
+            current.get_or_insert_with(|| (column, String::new())).1.push(c);
+        }
+    }
+    tokens.extend(current);
+    tokens
+}
 
-    Ok(Puzzle::new(my_stacks,
-                   instructions))
+fn parse_instruction(line_number: usize, line: &str, amount_of_stacks: usize) -> io::Result<Instruction> {
+    let cap = INSTRUCTION_RE.captures(line.trim())
+                            .ok_or_else(|| input_error(line_number,
+                                                       format!("expected 'move N from A to B' but found {:?}",
+                                                               line)))?;
+    let number = |i: usize| cap[i].parse::<usize>()
+                                  .map_err(|e| input_error(line_number, e));
+    let amount = number(1)?;
+    let from = number(2)?;
+    let to = number(3)?;
+    for stack in [from, to] {
+        if stack == 0 || stack > amount_of_stacks {
+            return Err(input_error(line_number,
+                                   format!("stack {} does not exist, there are {} stacks",
+                                           stack, amount_of_stacks)));
+        }
+    }
+    Ok(Instruction::new(line_number, amount, from, to))
 }
 
 fn contains_crate(line: &str) -> bool {
@@ -103,46 +252,260 @@ impl Puzzle {
         }
     }
 
-    fn solve1(&mut self) -> String {
-        self.instructions.iter().for_each(|instruction| {
+    fn solve(&self, crane: &mut dyn Crane) -> io::Result<String> {
+        let mut stacks = self.stacks.clone();
+        for instruction in self.instructions.iter() {
+            check_enough_crates(instruction, &stacks[instruction.from - 1])?;
+            crane.move_crates(&mut stacks, instruction);
+        }
+        Ok(top_crates(&stacks))
+    }
+}
 
-            let mut buffer: Vec<char> = Vec::new();
+fn top_crates(stacks: &[Stack]) -> String {
+    stacks.iter().filter_map(|stack|stack.peek()).collect::<String>()
+}
 
-            {
-                let from = self.stacks.get_mut(instruction.from - 1).unwrap();
-                (0..instruction.amount).for_each(|_| {
-                    buffer.push(from.pop_crate());
-                });
-            }
+// Draws the stacks in the same format read_input expects, labels included.
+fn render(stacks: &[Stack]) -> String {
+    let height = stacks.iter().map(|stack| stack.len()).max().unwrap_or(0);
+    let mut lines: Vec<String> = (0..height).rev().map(|y| {
+        stacks.iter()
+              .map(|stack| match stack.crates.get(y) {
+                  Some(c) => format!("[{}]", c),
+                  None => String::from("   "),
+              })
+              .collect::<Vec<String>>()
+              .join(" ")
+              .trim_end()
+              .to_string()
+    }).collect();
+    lines.push((1..=stacks.len()).map(|label| format!("{:^3}", label))
+                                 .collect::<Vec<String>>()
+                                 .join(" ")
+                                 .trim_end()
+                                 .to_string());
+    lines.join("\n")
+}
 
-            let to = self.stacks.get_mut(instruction.to - 1).unwrap();
-            for c in buffer {
-                to.push_crate(c);
-            }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReplayMode {
+    Off,
+    EveryStep,
+    AtStep(usize),
+    Interactive,
+}
+
+// What an instruction took away from and added to its stacks, enough to revert it.
+struct Undo {
+    from: usize,
+    to: usize,
+    taken: Vec<char>,
+    from_len: usize,
+    to_len: usize,
+}
+
+struct Replay<'a> {
+    puzzle: &'a Puzzle,
+    crane: Box<dyn Crane>,
+    stacks: Vec<Stack>,
+    history: Vec<Undo>,
+    position: usize,
+}
 
-        });
-        self.stacks.iter().map(|stack|stack.peek()).collect::<String>()
+impl<'a> Replay<'a> {
+    fn new(puzzle: &'a Puzzle, crane: Box<dyn Crane>) -> Self {
+        Replay {
+            puzzle,
+            crane,
+            stacks: puzzle.stacks.clone(),
+            history: Vec::new(),
+            position: 0,
+        }
     }
 
-    fn solve2(&mut self) -> String {
-        self.instructions.iter().for_each(|instruction| {
+    fn step_forward(&mut self) -> io::Result<bool> {
+        let instruction = match self.puzzle.instructions.get(self.position) {
+            Some(instruction) => instruction,
+            None => return Ok(false),
+        };
+        let from = &self.stacks[instruction.from - 1];
+        check_enough_crates(instruction, from)?;
+        let undo = Undo {
+            from: instruction.from - 1,
+            to: instruction.to - 1,
+            taken: from.crates[from.len() - instruction.amount..].to_vec(),
+            from_len: from.len(),
+            to_len: self.stacks[instruction.to - 1].len(),
+        };
+        self.crane.move_crates(&mut self.stacks, instruction);
+        self.history.push(undo);
+        self.position += 1;
+        Ok(true)
+    }
 
-            let mut buffer: Vec<char> = Vec::new();
+    fn step_back(&mut self) -> bool {
+        match self.history.pop() {
+            Some(undo) => {
+                self.stacks[undo.to].crates.truncate(undo.to_len);
+                let from = &mut self.stacks[undo.from];
+                from.crates.truncate(undo.from_len - undo.taken.len());
+                from.put_crates(&undo.taken);
+                self.position -= 1;
+                true
+            },
+            None => false,
+        }
+    }
 
-            {
-                let from = self.stacks.get_mut(instruction.from - 1).unwrap();
-                (0..instruction.amount).for_each(|_| {
-                    buffer.push(from.pop_crate());
-                });
-            }
+    fn go_to(&mut self, step: usize) -> io::Result<()> {
+        if step > self.puzzle.instructions.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      format!("there are only {} instructions",
+                                              self.puzzle.instructions.len())));
+        }
+        while self.position > step {
+            self.step_back();
+        }
+        while self.position < step {
+            self.step_forward()?;
+        }
+        Ok(())
+    }
 
-            let to = self.stacks.get_mut(instruction.to - 1).unwrap();
-            for c in buffer.iter().rev() {
-                to.push_crate(*c);
-            }
+    fn describe(&self) -> String {
+        match self.position {
+            0 => String::from("Starting stacks:"),
+            position => {
+                let instruction = &self.puzzle.instructions[position - 1];
+                format!("After instruction {} (move {} from {} to {}):",
+                        position, instruction.amount, instruction.from, instruction.to)
+            },
+        }
+    }
+}
+fn check_enough_crates(instruction: &Instruction, from: &Stack) -> io::Result<()> {
+    if from.len() < instruction.amount {
+        return Err(input_error(instruction.line_number,
+                               format!("cannot move {} crates from stack {}, it only holds {}",
+                                       instruction.amount, instruction.from, from.len())));
+    }
+    Ok(())
+}
+
+// A crane carries out a single instruction. The puzzle has already checked that the source
+// stack holds enough crates, so implementations only decide in which order they land.
+trait Crane {
+    fn move_crates(&mut self, stacks: &mut [Stack], instruction: &Instruction);
+
+    fn report(&self) -> Option<String> {
+        None
+    }
+}
+
+fn crane_from_name(name: &str) -> io::Result<Box<dyn Crane>> {
+    let (model, argument) = match name.split_once(':') {
+        Some((model, argument)) => (model, Some(argument)),
+        None => (name, None),
+    };
+    match (model, argument) {
+        ("9000", None) => Ok(Box::new(CrateMover9000)),
+        ("9001", None) => Ok(Box::new(CrateMover9001)),
+        ("limited", Some(capacity)) => match capacity.parse::<usize>() {
+            Ok(capacity) if capacity > 0 => Ok(Box::new(LimitedCrane::new(capacity))),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                    format!("{:?} is not a valid lift capacity", capacity))),
+        },
+        ("counting", Some(inner)) => Ok(Box::new(CountingCrane::new(crane_from_name(inner)?))),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                format!("unknown crane {:?}, expected 9000, 9001, limited:N or counting:CRANE",
+                                        name))),
+    }
+}
+
+// Moves crates one at a time, so a moved pile ends up reversed.
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn move_crates(&mut self, stacks: &mut [Stack], instruction: &Instruction) {
+        let mut buffer = stacks[instruction.from - 1].take_crates(instruction.amount);
+        buffer.reverse();
+        stacks[instruction.to - 1].put_crates(&buffer);
+    }
+}
 
-        });
-        self.stacks.iter().map(|stack|stack.peek()).collect::<String>()
+// Moves the whole pile at once and keeps its order.
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn move_crates(&mut self, stacks: &mut [Stack], instruction: &Instruction) {
+        let buffer = stacks[instruction.from - 1].take_crates(instruction.amount);
+        stacks[instruction.to - 1].put_crates(&buffer);
+    }
+}
+
+// Like the 9001, but a pile larger than its capacity is moved in several lifts.
+struct LimitedCrane {
+    capacity: usize,
+    lifts: usize,
+}
+
+impl LimitedCrane {
+    fn new(capacity: usize) -> Self {
+        LimitedCrane {
+            capacity,
+            lifts: 0,
+        }
+    }
+}
+
+impl Crane for LimitedCrane {
+    fn move_crates(&mut self, stacks: &mut [Stack], instruction: &Instruction) {
+        let mut remaining = instruction.amount;
+        while remaining > 0 {
+            let lift = remaining.min(self.capacity);
+            let buffer = stacks[instruction.from - 1].take_crates(lift);
+            stacks[instruction.to - 1].put_crates(&buffer);
+            remaining -= lift;
+            self.lifts += 1;
+        }
+    }
+
+    fn report(&self) -> Option<String> {
+        Some(format!("{:?} lifts of at most {:?} crates were needed", self.lifts, self.capacity))
+    }
+}
+
+// Wraps another crane and keeps track of the work it did.
+struct CountingCrane {
+    inner: Box<dyn Crane>,
+    instructions: usize,
+    crates_moved: usize,
+}
+
+impl CountingCrane {
+    fn new(inner: Box<dyn Crane>) -> Self {
+        CountingCrane {
+            inner,
+            instructions: 0,
+            crates_moved: 0,
+        }
+    }
+}
+
+impl Crane for CountingCrane {
+    fn move_crates(&mut self, stacks: &mut [Stack], instruction: &Instruction) {
+        self.inner.move_crates(stacks, instruction);
+        self.instructions += 1;
+        self.crates_moved += instruction.amount;
+    }
+
+    fn report(&self) -> Option<String> {
+        let cost = format!("{:?} crates were moved in {:?} instructions", self.crates_moved, self.instructions);
+        match self.inner.report() {
+            Some(inner) => Some(format!("{}\n{}", cost, inner)),
+            None => Some(cost),
+        }
     }
 }
 
@@ -162,25 +525,36 @@ impl Stack {
         self.crates.push(c)
     }
 
-    fn pop_crate(&mut self) -> char {
-        self.crates.pop().unwrap()
+    // Removes the top `amount` crates, returned bottom to top.
+    fn take_crates(&mut self, amount: usize) -> Vec<char> {
+        self.crates.split_off(self.crates.len() - amount)
+    }
+
+    fn put_crates(&mut self, crates: &[char]) {
+        self.crates.extend_from_slice(crates)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.crates.last().copied()
     }
 
-    fn peek(&self) -> char {
-        self.crates.last().unwrap().clone()
+    fn len(&self) -> usize {
+        self.crates.len()
     }
 }
 
 #[derive(Debug, Clone)]
 struct Instruction {
+    line_number: usize,
     amount: usize,
     from: usize,
     to: usize,
 }
 
 impl  Instruction {
-    fn new(amount: usize, from: usize, to: usize)  -> Self {
+    fn new(line_number: usize, amount: usize, from: usize, to: usize)  -> Self {
         Instruction {
+            line_number,
             amount,
             from,
             to
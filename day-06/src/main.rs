@@ -1,42 +1,241 @@
 use std::fs::File;
-use std::io::{self, BufReader, BufRead};
+use std::io::{self, BufReader, BufRead, Read};
 use std::env;
-use itertools::Itertools;
 
+const START_OF_PACKET: usize = 4;
+const START_OF_MESSAGE: usize = 14;
 
 fn main() ->  io::Result<()> {
 
     let args: Vec<String> = env::args().collect();
     let input = &args[1];
 
-    read_input(input)?.iter()
-                              .for_each(|s| {
-                                println!("{:?} characters need to be processed before the first start-of-packet marker is detected.",
-                                         find_start_of_pattern(s,4 as usize));
-                                  println!("{:?} characters need to be processed before the first start-of-message marker is detected.",
-                                         find_start_of_pattern(s,14 as usize))
-                              });
+    let mut window_sizes: Vec<usize> = Vec::new();
+    let mut frames = false;
+    let mut options = args.iter().skip(2);
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--window" => window_sizes.push(options.next()
+                                                   .and_then(|x| x.parse::<usize>().ok())
+                                                   .filter(|x| *x > 0)
+                                                   .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+                                                                                 "--window expects a positive number"))?),
+            "--frames" => frames = true,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                           format!("unknown option {}", option))),
+        }
+    }
+    if frames {
+        let window_size = window_sizes.last().copied().unwrap_or(START_OF_MESSAGE);
+        print_frames(&frame_streams(open_input(input)?, window_size)?);
+        return Ok(());
+    }
+    if window_sizes.is_empty() {
+        window_sizes = vec![START_OF_PACKET, START_OF_MESSAGE];
+    }
+
+    find_markers(open_input(input)?, &window_sizes)?
+        .iter()
+        .for_each(|markers| {
+            window_sizes.iter().zip(markers.iter()).for_each(|(window_size, marker)| {
+                let kind = match *window_size {
+                    START_OF_PACKET => String::from("start-of-packet marker"),
+                    START_OF_MESSAGE => String::from("start-of-message marker"),
+                    _ => format!("marker of {} distinct characters", window_size),
+                };
+                match marker {
+                    Some(position) =>
+                        println!("{:?} characters need to be processed before the first {} is detected.",
+                                 position, kind),
+                    None => println!("No {} was detected.", kind),
+                }
+            })
+        });
 
     Ok(())
 }
 
-fn find_start_of_pattern(buffer: &Vec<char>, window_size: usize) -> usize {
-    buffer.windows(window_size)
-          .enumerate()
-          .skip_while(|slice| {
-              let x =  (*slice).1.iter().map(|x|x.clone()).collect::<Vec<char>>();
-              let set = x.iter().unique().collect::<Vec<&char>>();
-              set.len() < window_size
-          }).take(1)
-            .fold(0, |_sum, val| val.0) + // The window
-             (window_size - 1) + // We look 'window_size' chars ahead
-             (1 as usize)  // This is an index not a position
-}
-
-fn read_input(filename: &String) -> io::Result<Vec<Vec<char>>> {
-    let file_in = File::open(filename)?;
-    Ok(BufReader::new(file_in)
-                 .lines()
-                 .map(|s|s.unwrap().chars().collect::<Vec<char>>())
-                 .collect::<Vec<Vec<char>>>())
+// "-" reads from stdin, so streams don't have to be stored in a file first.
+fn open_input(filename: &String) -> io::Result<Box<dyn Read>> {
+    if filename == "-" {
+        Ok(Box::new(io::stdin()))
+    } else {
+        Ok(Box::new(File::open(filename)?))
+    }
+}
+
+// Every line is a separate datastream. For each of them returns, per window size, the
+// number of bytes processed when the first marker of that size is complete.
+fn find_markers<R: Read>(reader: R, window_sizes: &[usize]) -> io::Result<Vec<Vec<Option<u64>>>> {
+    let mut detectors: Vec<MarkerDetector> =
+        window_sizes.iter().map(|window_size| MarkerDetector::new(*window_size)).collect();
+    let mut markers: Vec<Option<u64>> = vec![None; window_sizes.len()];
+    let mut streams: Vec<Vec<Option<u64>>> = Vec::new();
+    let mut stream_is_empty = true;
+
+    for_each_byte(reader, |byte| match byte {
+        b'\n' => {
+            streams.push(markers.clone());
+            markers.iter_mut().for_each(|marker| *marker = None);
+            detectors.iter_mut().for_each(|detector| detector.reset());
+            stream_is_empty = true;
+        },
+        b'\r' => {},
+        _ => {
+            stream_is_empty = false;
+            for (detector, marker) in detectors.iter_mut().zip(markers.iter_mut()) {
+                if detector.push(byte) && marker.is_none() {
+                    *marker = Some(detector.position());
+                }
+            }
+        },
+    })?;
+    if !stream_is_empty {
+        streams.push(markers);
+    }
+    Ok(streams)
+}
+
+// Splits every line into the messages that follow each marker of `window_size` distinct
+// bytes. Bytes in front of the first marker are not part of any message.
+fn frame_streams<R: Read>(reader: R, window_size: usize) -> io::Result<Vec<Vec<Frame>>> {
+    let mut detector = MarkerDetector::new(window_size);
+    let mut streams: Vec<Vec<Frame>> = Vec::new();
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut pending: Vec<u8> = Vec::new();
+    let mut offset: u64 = 0;
+    let mut stream_is_empty = true;
+
+    let finish_frame = |frames: &mut Vec<Frame>, body: &[u8]| {
+        if let Some(frame) = frames.last_mut() {
+            frame.body = body.to_vec();
+        }
+    };
+
+    for_each_byte(reader, |byte| match byte {
+        b'\n' => {
+            finish_frame(&mut frames, &pending);
+            streams.push(std::mem::take(&mut frames));
+            pending.clear();
+            detector.reset();
+            offset = 0;
+            stream_is_empty = true;
+        },
+        b'\r' => {},
+        _ => {
+            stream_is_empty = false;
+            pending.push(byte);
+            offset += 1;
+            if detector.push(byte) {
+                finish_frame(&mut frames, &pending[..pending.len() - window_size]);
+                frames.push(Frame {
+                    marker_offset: offset - window_size as u64,
+                    body_offset: offset,
+                    body: Vec::new(),
+                });
+                pending.clear();
+                detector.reset();
+            }
+        },
+    })?;
+    if !stream_is_empty {
+        finish_frame(&mut frames, &pending);
+        streams.push(frames);
+    }
+    Ok(streams)
+}
+
+fn print_frames(streams: &[Vec<Frame>]) {
+    for (stream, frames) in streams.iter().enumerate() {
+        println!("Stream {}:", stream + 1);
+        for (i, frame) in frames.iter().enumerate() {
+            println!("  message {} after marker at {}: bytes {}..{} {:?}",
+                     i + 1,
+                     frame.marker_offset,
+                     frame.body_offset,
+                     frame.body_offset + frame.body.len() as u64,
+                     String::from_utf8_lossy(&frame.body));
+        }
+        let distances: Vec<u64> = frames.windows(2)
+                                        .map(|x| x[1].marker_offset - x[0].marker_offset)
+                                        .collect();
+        match (distances.iter().min(), distances.iter().max()) {
+            (Some(min), Some(max)) =>
+                println!("  {:?} markers, {:?} to {:?} bytes apart, {:.1} on average",
+                         frames.len(), min, max,
+                         distances.iter().sum::<u64>() as f64 / distances.len() as f64),
+            _ => println!("  {:?} markers", frames.len()),
+        }
+    }
+}
+
+struct Frame {
+    marker_offset: u64,
+    body_offset: u64,
+    body: Vec<u8>,
+}
+
+fn for_each_byte<R: Read, F: FnMut(u8)>(reader: R, mut on_byte: F) -> io::Result<()> {
+    let mut reader = BufReader::with_capacity(1 << 16, reader);
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            return Ok(());
+        }
+        buffer.iter().for_each(|byte| on_byte(*byte));
+        let consumed = buffer.len();
+        reader.consume(consumed);
+    }
+}
+
+// Sliding window over a byte stream that knows in O(1) per byte whether the last
+// `window_size` bytes are all different.
+struct MarkerDetector {
+    window_size: usize,
+    window: Vec<u8>,
+    counts: [usize; 256],
+    duplicates: usize,
+    position: u64,
+}
+
+impl MarkerDetector {
+    fn new(window_size: usize) -> Self {
+        MarkerDetector {
+            window_size,
+            window: vec![0; window_size],
+            counts: [0; 256],
+            duplicates: 0,
+            position: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.counts = [0; 256];
+        self.duplicates = 0;
+        self.position = 0;
+    }
+
+    // Bytes processed so far, which is where a marker ends.
+    fn position(&self) -> u64 {
+        self.position
+    }
+
+    // Adds a byte and tells whether the window now forms a marker.
+    fn push(&mut self, byte: u8) -> bool {
+        let slot = (self.position % self.window_size as u64) as usize;
+        if self.position >= self.window_size as u64 {
+            let leaving = self.window[slot] as usize;
+            self.counts[leaving] -= 1;
+            if self.counts[leaving] == 1 {
+                self.duplicates -= 1;
+            }
+        }
+        self.window[slot] = byte;
+        self.counts[byte as usize] += 1;
+        if self.counts[byte as usize] == 2 {
+            self.duplicates += 1;
+        }
+        self.position += 1;
+        self.position >= self.window_size as u64 && self.duplicates == 0
+    }
 }
@@ -1,12 +1,13 @@
 use std::fs::File;
 use std::io::{self, BufReader, BufRead};
 use std::env;
+use std::cell::OnceCell;
+use std::collections::HashMap;
 use lazy_static::lazy_static;
 use regex::Regex;
 
 
 lazy_static! {
-        static ref CD_CMD: Regex = Regex::new(r"^\$ cd\s+([\w\./]+)$").unwrap();
         static ref DIR_RESULT: Regex = Regex::new(r"^dir\s+([\w\.]+)$").unwrap();
         static ref FILE_RESULT: Regex = Regex::new(r"^(\d+)\s+([\w\.]+)$").unwrap();
 }
@@ -15,37 +16,265 @@ fn main() ->  io::Result<()> {
 
     let args: Vec<String> = env::args().collect();
     let node_manager = read_input(&args[1])?;
+
+    let mut disk_size: usize = 70000000;
+    let mut target_free_space: usize = 30000000;
+    let mut objective: Option<Objective> = None;
+    let mut options = args.iter().skip(2);
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--tree" => print!("{}", node_manager.tree()),
+            "--du" => node_manager.du().iter().for_each(|(size, path)| println!("{}\t{}", size, path)),
+            "--json" => println!("{}", node_manager.to_json()),
+            "--query" => {
+                let query = options.next()
+                                   .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+                                                                 "--query expects a query"))?;
+                let query = Query::parse(query)
+                                  .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                node_manager.find(&query).iter()
+                            .for_each(|node_id| println!("{}\t{}", node_manager.size(*node_id),
+                                                         node_manager.path(*node_id)));
+            },
+            "--disk" => disk_size = parse_size(option, options.next())?,
+            "--target" => target_free_space = parse_size(option, options.next())?,
+            "--plan" => objective = Some(match options.next().map(|x| x.as_str()) {
+                Some("bytes") => Objective::Bytes,
+                Some("count") => Objective::Count,
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                               "--plan expects bytes or count")),
+            }),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                           format!("unknown option {}", option))),
+        }
+    }
+
     println!("{:?} is the sum of the total sizes of those directories", node_manager.solve1());
 
-    println!("{:?} is the total size of that directory", node_manager.solve2());
+    match node_manager.solve2(disk_size, target_free_space) {
+        Some(size) => println!("{:?} is the total size of that directory", size),
+        None => println!("No single directory frees enough space"),
+    }
+
+    if let Some(objective) = objective {
+        let plan = node_manager.plan_cleanup(disk_size, target_free_space, objective)
+                               .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        print!("{}", node_manager.describe_plan(&plan));
+    }
     Ok(())
 }
 
+// A `find` query: every option narrows the result, options left out match everything.
+//   find [--type dir|file] [--min-size N] [--max-size N] [--name GLOB] [--path GLOB]
+// Globs use `*` and `?` within a name; in paths `**` also matches any number of directories.
+#[derive(Debug, Default)]
+struct Query {
+    is_dir: Option<bool>,
+    min_size: Option<usize>,
+    max_size: Option<usize>,
+    name: Option<String>,
+    path: Option<Vec<String>>,
+}
+
+impl Query {
+    fn parse(text: &str) -> Result<Query, String> {
+        let words = split_words(text)?;
+        let mut words = words.iter();
+        if words.next().map(|x| x.as_str()) != Some("find") {
+            return Err(String::from("queries start with find"));
+        }
+        let mut query = Query::default();
+        while let Some(word) = words.next() {
+            let mut value = || words.next()
+                                    .ok_or_else(|| format!("{} expects a value", word));
+            match word.as_str() {
+                "--type" => query.is_dir = match value()?.as_str() {
+                    "dir" | "d" => Some(true),
+                    "file" | "f" => Some(false),
+                    kind => return Err(format!("unknown type {:?}, expected dir or file", kind)),
+                },
+                "--min-size" => query.min_size = Some(parse_query_size(value()?)?),
+                "--max-size" => query.max_size = Some(parse_query_size(value()?)?),
+                "--name" => query.name = Some(value()?.clone()),
+                "--path" => {
+                    let pattern = value()?;
+                    let pattern = if pattern.starts_with('/') { pattern.clone() } else { format!("/**/{}", pattern) };
+                    query.path = Some(pattern.split('/')
+                                             .filter(|x| !x.is_empty())
+                                             .map(|x| x.to_string())
+                                             .collect());
+                },
+                _ => return Err(format!("unknown find option {:?}", word)),
+            }
+        }
+        Ok(query)
+    }
+}
+
+fn parse_query_size(value: &str) -> Result<usize, String> {
+    value.parse::<usize>().map_err(|e| format!("invalid size {:?}: {}", value, e))
+}
+
+// Splits on whitespace, keeping text inside single or double quotes together.
+fn split_words(text: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote: Option<char> = None;
+    for c in text.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.get_or_insert_with(String::new).push(c),
+            (None, '\'') | (None, '"') => {
+                quote = Some(c);
+                current.get_or_insert_with(String::new);
+            },
+            (None, c) if c.is_whitespace() => words.extend(current.take()),
+            (None, c) => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err(String::from("unterminated quote"));
+    }
+    words.extend(current);
+    Ok(words)
+}
+
+fn glob_matches(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|i| glob_matches(rest, &text[i..])),
+        Some(('?', rest)) => !text.is_empty() && glob_matches(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && glob_matches(rest, &text[1..]),
+    }
+}
+
+fn name_matches(pattern: &str, name: &str) -> bool {
+    glob_matches(&pattern.chars().collect::<Vec<char>>(), &name.chars().collect::<Vec<char>>())
+}
+
+fn path_matches(pattern: &[String], segments: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => segments.is_empty(),
+        Some((first, rest)) if first == "**" =>
+            (0..=segments.len()).any(|i| path_matches(rest, &segments[i..])),
+        Some((first, rest)) =>
+            !segments.is_empty() && name_matches(first, segments[0]) && path_matches(rest, &segments[1..]),
+    }
+}
+
+fn parse_size(option: &str, value: Option<&String>) -> io::Result<usize> {
+    value.and_then(|x| x.parse::<usize>().ok())
+         .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+                                       format!("{} expects a size in bytes", option)))
+}
+
 fn read_input(filename: &String) -> io::Result<NodeManager> {
     let mut node_manager = NodeManager::new();
+    let interpreter = Interpreter::new();
 
     let file_in = File::open(filename)?;
-    BufReader::new(file_in).lines().map(|x| x.unwrap()).for_each(|line| {
-        if CD_CMD.is_match(line.as_str()) {
-            let mut cap = CD_CMD.captures_iter(line.as_str());
-            let cap  = cap.next().unwrap();
-            node_manager.set_active_node(&cap[1]);
-        } else if line.starts_with("$ ls") {
-            // IGNORE
-        } else if DIR_RESULT.is_match(line.as_str()) {
-            let mut cap = DIR_RESULT.captures_iter(line.as_str());
-            let cap  = cap.next().unwrap();
-            node_manager.add_dir_to_current_node(&cap[1]);
-        } else if FILE_RESULT.is_match(line.as_str()) {
-            let mut cap = FILE_RESULT.captures_iter(line.as_str());
-            let cap  = cap.next().unwrap();
-            node_manager.add_file_to_current_node(&cap[1].parse::<usize>().unwrap(),
-                                                &cap[2]);
+    for (line_number, line) in BufReader::new(file_in).lines().enumerate() {
+        interpreter.run_line(&mut node_manager, line?.as_str())
+                   .map_err(|e| io::Error::new(io::ErrorKind::InvalidData,
+                                               format!("line {}: {}", line_number + 1, e)))?;
+    }
+    Ok(node_manager)
+}
+
+type Command = fn(&mut NodeManager, &[&str]) -> Result<(), String>;
+
+// Lines starting with `$` are looked up in the command table, everything else is treated
+// as output of the last `ls`. New commands only need to be registered.
+struct Interpreter {
+    commands: HashMap<&'static str, Command>,
+}
+
+impl Interpreter {
+    fn new() -> Self {
+        let mut interpreter = Interpreter {
+            commands: HashMap::new(),
+        };
+        interpreter.register("cd", cd);
+        interpreter.register("ls", ls);
+        interpreter.register("mkdir", mkdir);
+        interpreter.register("rm", rm);
+        interpreter.register("mv", mv);
+        interpreter.register("touch", touch);
+        interpreter
+    }
+
+    fn register(&mut self, name: &'static str, command: Command) {
+        self.commands.insert(name, command);
+    }
+
+    fn run_line(&self, node_manager: &mut NodeManager, line: &str) -> Result<(), String> {
+        if let Some(command_line) = line.strip_prefix('$') {
+            let words = command_line.split_whitespace().collect::<Vec<&str>>();
+            let name = words.first().ok_or("missing command after $")?;
+            let command = self.commands.get(name)
+                                       .ok_or_else(|| format!("unknown command {:?}", name))?;
+            command(node_manager, &words[1..])
+        } else if let Some(cap) = DIR_RESULT.captures(line) {
+            node_manager.add_dir_to_current_node(&cap[1])
+        } else if let Some(cap) = FILE_RESULT.captures(line) {
+            let size = cap[1].parse::<usize>().map_err(|e| e.to_string())?;
+            node_manager.add_file_to_current_node(&size, &cap[2])
         } else {
-            panic!("Unexpected line {:?} ..", line)
+            Err(format!("unexpected line {:?}", line))
         }
-    });
-    Ok(node_manager)
+    }
+}
+
+fn expect_arguments(command: &str, arguments: &[&str], usage: &str, counts: &[usize]) -> Result<(), String> {
+    if counts.contains(&arguments.len()) {
+        Ok(())
+    } else {
+        Err(format!("usage: {} {}", command, usage))
+    }
+}
+
+fn cd(node_manager: &mut NodeManager, arguments: &[&str]) -> Result<(), String> {
+    expect_arguments("cd", arguments, "PATH", &[1])?;
+    node_manager.set_active_node(arguments[0])
+}
+
+fn ls(_: &mut NodeManager, arguments: &[&str]) -> Result<(), String> {
+    expect_arguments("ls", arguments, "", &[0])
+}
+
+fn mkdir(node_manager: &mut NodeManager, arguments: &[&str]) -> Result<(), String> {
+    expect_arguments("mkdir", arguments, "PATH", &[1])?;
+    node_manager.resolve(arguments[0], true).map(|_| ())
+}
+
+fn rm(node_manager: &mut NodeManager, arguments: &[&str]) -> Result<(), String> {
+    expect_arguments("rm", arguments, "PATH", &[1])?;
+    let node_id = node_manager.resolve(arguments[0], false)?;
+    node_manager.remove(node_id)
+}
+
+fn mv(node_manager: &mut NodeManager, arguments: &[&str]) -> Result<(), String> {
+    expect_arguments("mv", arguments, "SOURCE TARGET", &[2])?;
+    let node_id = node_manager.resolve(arguments[0], false)?;
+    node_manager.move_node(node_id, arguments[1])
+}
+
+fn touch(node_manager: &mut NodeManager, arguments: &[&str]) -> Result<(), String> {
+    expect_arguments("touch", arguments, "PATH [SIZE]", &[1, 2])?;
+    let size = match arguments.get(1) {
+        Some(size) => size.parse::<usize>().map_err(|e| format!("invalid size {:?}: {}", size, e))?,
+        None => 0,
+    };
+    let (parent, name) = node_manager.resolve_parent(arguments[0])?;
+    match node_manager.child_by_name(parent, name) {
+        Some(node_id) if node_manager.nodes[node_id].is_dir() =>
+            Err(format!("{} is a directory", node_manager.path(node_id))),
+        Some(node_id) => {
+            node_manager.set_file_size(node_id, size);
+            Ok(())
+        },
+        None => node_manager.add_child(parent, name, Some(size)).map(|_| ()),
+    }
 }
 
 type NodeId = usize;
@@ -54,6 +283,8 @@ type NodeId = usize;
 pub struct NodeManager {
     nodes: Vec<Node>,
     current_node_id: NodeId,
+    // Recursive sizes per node, computed in one bottom-up pass and dropped on every change.
+    sizes: OnceCell<Vec<usize>>,
 }
 
 impl NodeManager {
@@ -61,6 +292,7 @@ impl NodeManager {
         let mut node_manager = NodeManager {
             nodes: Vec::new(),
             current_node_id: 0,
+            sizes: OnceCell::new(),
         };
         node_manager.create_node(None, "/", None);
         node_manager
@@ -71,6 +303,7 @@ impl NodeManager {
                    name: &str,
                    size: Option<usize>) -> NodeId {
         let id =  self.nodes.len();
+        self.sizes.take();
         self.nodes.push(Node::new(parent,
                                         name.to_string(),
                                        size));
@@ -78,86 +311,529 @@ impl NodeManager {
     }
 
     fn root(&self) -> NodeId {
-        return 0
+        0
+    }
+
+    fn set_active_node(&mut self, path: &str) -> Result<(), String> {
+        self.current_node_id = self.resolve(path, true)?;
+        Ok(())
+    }
+
+    fn add_dir_to_current_node(&mut self, name :&str) -> Result<(), String> {
+        match self.child_by_name(self.current_node_id, name) {
+            Some(node_id) if !self.nodes[node_id].is_dir() =>
+                Err(format!("{} is a file, not a directory", self.path(node_id))),
+            Some(_) => Ok(()),
+            None => self.add_child(self.current_node_id, name, None).map(|_| ()),
+        }
+    }
+
+    fn add_file_to_current_node(&mut self, size: &usize, name :&str) -> Result<(), String> {
+        match self.child_by_name(self.current_node_id, name) {
+            Some(node_id) if self.nodes[node_id].is_dir() =>
+                Err(format!("{} is a directory, not a file", self.path(node_id))),
+            Some(_) => Ok(()),
+            None => self.add_child(self.current_node_id, name, Some(*size)).map(|_| ()),
+        }
     }
 
-    fn set_active_node(&mut self, name: &str) {
-        self.current_node_id =
-            if name == "/" {
-                self.root()
-            } else if name == ".." {
-                self.nodes[self.current_node_id].parent.unwrap()
+    fn add_child(&mut self, parent: NodeId, name: &str, size: Option<usize>) -> Result<NodeId, String> {
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            return Err(format!("{:?} is not a valid name", name));
+        }
+        let child_node = self.create_node(Some(parent), name, size);
+        self.nodes[parent].children.push(child_node);
+        Ok(child_node)
+    }
+
+    fn set_file_size(&mut self, node_id: NodeId, size: usize) {
+        self.sizes.take();
+        self.nodes[node_id].size = Some(size);
+    }
+
+    fn child_by_name(&self, parent: NodeId, name: &str) -> Option<NodeId> {
+        self.nodes[parent].children
+            .iter()
+            .find(|child_id| self.nodes[**child_id].name == name)
+            .copied()
+    }
+
+    // Follows a path relative to the current directory, or to the root if it starts with
+    // a slash. With `create` missing directories are made along the way and the path has
+    // to end in a directory.
+    fn resolve(&mut self, path: &str, create: bool) -> Result<NodeId, String> {
+        let mut node_id = if path.starts_with('/') { self.root() } else { self.current_node_id };
+        for segment in path.split('/').filter(|x| !x.is_empty() && *x != ".") {
+            if !self.nodes[node_id].is_dir() {
+                return Err(format!("{} is not a directory", self.path(node_id)));
+            }
+            node_id = if segment == ".." {
+                self.nodes[node_id].parent.unwrap_or(node_id)
             } else {
-                *self.get_child_node_by_name(name).unwrap()
+                match self.child_by_name(node_id, segment) {
+                    Some(child_id) => child_id,
+                    None if create => self.add_child(node_id, segment, None)?,
+                    None => return Err(format!("{}/{} does not exist",
+                                               self.path(node_id).trim_end_matches('/'), segment)),
+                }
+            };
+        }
+        if create && !self.nodes[node_id].is_dir() {
+            return Err(format!("{} is not a directory", self.path(node_id)));
+        }
+        Ok(node_id)
+    }
+
+    // The directory a path lives in, created if needed, and the last segment of the path.
+    fn resolve_parent<'a>(&mut self, path: &'a str) -> Result<(NodeId, &'a str), String> {
+        let path = path.trim_end_matches('/');
+        let (parent, name) = match path.rfind('/') {
+            Some(i) => (self.resolve(if i == 0 { "/" } else { &path[..i] }, true)?, &path[i + 1..]),
+            None => (self.current_node_id, path),
+        };
+        if !self.nodes[parent].is_dir() {
+            return Err(format!("{} is not a directory", self.path(parent)));
+        }
+        Ok((parent, name))
+    }
+
+    fn is_ancestor_of(&self, ancestor: NodeId, node_id: NodeId) -> bool {
+        let mut current = Some(node_id);
+        while let Some(id) = current {
+            if id == ancestor {
+                return true;
             }
+            current = self.nodes[id].parent;
+        }
+        false
+    }
+
+    fn detach(&mut self, node_id: NodeId) {
+        self.sizes.take();
+        if let Some(parent) = self.nodes[node_id].parent.take() {
+            self.nodes[parent].children.retain(|child_id| *child_id != node_id);
+        }
+    }
+
+    // Removed nodes stay in `nodes` but are no longer reachable from the root.
+    fn remove(&mut self, node_id: NodeId) -> Result<(), String> {
+        if self.is_ancestor_of(node_id, self.current_node_id) {
+            return Err(format!("cannot remove {}, it contains the current directory", self.path(node_id)));
+        }
+        self.detach(node_id);
+        Ok(())
     }
 
-    fn add_dir_to_current_node(&mut self, name :&str) {
-        if self.get_child_node_by_name(name).is_some() {
-            return
+    // Moves into `target` when it is an existing directory, otherwise renames to it.
+    fn move_node(&mut self, node_id: NodeId, target: &str) -> Result<(), String> {
+        if node_id == self.root() {
+            return Err(String::from("cannot move the root directory"));
         }
-        let child_node = self.create_node(Some(self.current_node_id),
-                                                    name,
-                                                None);
-        self.nodes[self.current_node_id]
-            .children.push(child_node);
+        let (parent, name) = match self.resolve(target, false) {
+            Ok(target_id) if self.nodes[target_id].is_dir() =>
+                (target_id, self.nodes[node_id].name.clone()),
+            Ok(target_id) => return Err(format!("{} already exists", self.path(target_id))),
+            Err(_) => {
+                let (parent, name) = self.resolve_parent(target)?;
+                (parent, name.to_string())
+            },
+        };
+        if self.is_ancestor_of(node_id, parent) {
+            return Err(format!("cannot move {} into itself", self.path(node_id)));
+        }
+        if let Some(existing) = self.child_by_name(parent, name.as_str()) {
+            return Err(format!("{} already exists", self.path(existing)));
+        }
+        self.detach(node_id);
+        self.sizes.take();
+        self.nodes[node_id].name = name;
+        self.nodes[node_id].parent = Some(parent);
+        self.nodes[parent].children.push(node_id);
+        Ok(())
+    }
+
+    // Every node still reachable from the root, parents before children.
+    fn node_ids(&self) -> Vec<NodeId> {
+        let mut ids = Vec::new();
+        let mut stack = vec![self.root()];
+        while let Some(node_id) = stack.pop() {
+            ids.push(node_id);
+            stack.extend(self.nodes[node_id].children.iter().rev());
+        }
+        ids
+    }
+
+    fn path(&self, node_id: NodeId) -> String {
+        let mut names: Vec<&str> = Vec::new();
+        let mut current = node_id;
+        while let Some(parent) = self.nodes[current].parent {
+            names.push(self.nodes[current].name.as_str());
+            current = parent;
+        }
+        names.reverse();
+        format!("/{}", names.join("/"))
+    }
+
+    fn sorted_children(&self, node_id: NodeId) -> Vec<NodeId> {
+        let mut children = self.nodes[node_id].children.clone();
+        children.sort_by(|l, r| self.nodes[*l].name.cmp(&self.nodes[*r].name));
+        children
+    }
+
+    // Indented listing in the style of `tree`, with the recursive size of every entry.
+    fn tree(&self) -> String {
+        let mut out = format!("{} ({})\n", self.nodes[self.root()].name, self.size(self.root()));
+        self.tree_children(self.root(), "", &mut out);
+        out
     }
 
-    fn add_file_to_current_node(&mut self, size: &usize, name :&str) {
-        if self.get_child_node_by_name(name).is_some() {
-            return
+    fn tree_children(&self, node_id: NodeId, prefix: &str, out: &mut String) {
+        let children = self.sorted_children(node_id);
+        for (i, child_id) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            let child = &self.nodes[*child_id];
+            let kind = if child.is_dir() { "/" } else { "" };
+            out.push_str(format!("{}{} {}{} ({})\n",
+                                 prefix,
+                                 if last { "└──" } else { "├──" },
+                                 child.name,
+                                 kind,
+                                 self.size(*child_id)).as_str());
+            self.tree_children(*child_id,
+                               format!("{}{}", prefix, if last { "    " } else { "│   " }).as_str(),
+                               out);
         }
-        let child_node = self.create_node(Some(self.current_node_id),
-                                                        name,
-                                                    Some(*size));
-        self.nodes[self.current_node_id]
-            .children.push(child_node);
     }
 
-    fn get_child_node_by_name(&self,
-                              name: &str) -> Option<&usize> {
-        let children =
-            self.nodes.get(self.current_node_id)
-                .unwrap()
-                .children
-                .iter()
-                .filter(|child_id| self.nodes
-                    .get(**child_id)
-                    .unwrap().name == name)
-                .take(1).collect::<Vec<&usize>>();
-        if children.len() > 0 {
-            Some(*children.get(0).unwrap())
-        }  else {
-            None
+    // Every file and directory with its size, largest first, like `du -a`.
+    fn du(&self) -> Vec<(usize, String)> {
+        let mut entries = self.node_ids().into_iter()
+            .map(|node_id| (self.size(node_id), self.path(node_id)))
+            .collect::<Vec<(usize, String)>>();
+        entries.sort_by(|l, r| r.0.cmp(&l.0).then_with(|| l.1.cmp(&r.1)));
+        entries
+    }
+
+    fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.node_to_json(self.root(), &mut out);
+        out
+    }
+
+    fn node_to_json(&self, node_id: NodeId, out: &mut String) {
+        let node = &self.nodes[node_id];
+        out.push_str(format!("{{\"name\":{},\"type\":\"{}\",\"size\":{}",
+                             json_string(node.name.as_str()),
+                             if node.is_dir() { "dir" } else { "file" },
+                             self.size(node_id)).as_str());
+        if node.is_dir() {
+            out.push_str(",\"children\":[");
+            for (i, child_id) in self.sorted_children(node_id).iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                self.node_to_json(*child_id, out);
+            }
+            out.push(']');
         }
+        out.push('}');
+    }
+
+    fn find(&self, query: &Query) -> Vec<NodeId> {
+        self.node_ids().into_iter()
+            .filter(|node_id| {
+                let node = &self.nodes[*node_id];
+                let size = self.size(*node_id);
+                query.is_dir.is_none_or(|is_dir| node.is_dir() == is_dir)
+                    && query.min_size.is_none_or(|min_size| size >= min_size)
+                    && query.max_size.is_none_or(|max_size| size <= max_size)
+                    && query.name.as_ref().is_none_or(|name| name_matches(name, node.name.as_str()))
+                    && query.path.as_ref().is_none_or(|pattern| {
+                        let path = self.path(*node_id);
+                        let segments = path.split('/').filter(|x| !x.is_empty()).collect::<Vec<&str>>();
+                        path_matches(pattern, &segments)
+                    })
+            })
+            .collect()
     }
 
     fn solve1(&self) -> usize {
         let max_size: usize = 100000;
-        self.nodes.iter()
-                  .filter(|node| node.size.is_none())
-                  .map(|node| node.size(self))
+        self.node_ids().into_iter()
+                  .filter(|node_id| self.nodes[*node_id].is_dir())
+                  .map(|node_id| self.size(node_id))
                   .filter(|size| *size <= max_size)
-                  .fold(0, |sum, val| sum + val)
+                  .sum()
+    }
+
+    fn solve2(&self, system_size: usize, needed_size: usize) -> Option<usize> {
+        let current_size: usize = system_size.saturating_sub(self.size(self.root()));
+
+        self.node_ids().into_iter()
+                  .filter(|node_id| self.nodes[*node_id].is_dir())
+                  .map(|node_id| self.size(node_id))
+                  .filter(|size| *size + current_size >= needed_size)
+                  .min()
+    }
+
+    fn sizes(&self) -> &[usize] {
+        self.sizes.get_or_init(|| {
+            let mut sizes = vec![0; self.nodes.len()];
+            for node_id in self.node_ids().into_iter().rev() {
+                let node = &self.nodes[node_id];
+                sizes[node_id] = match node.size {
+                    Some(size) => size,
+                    None => node.children.iter().map(|child_id| sizes[*child_id]).sum(),
+                };
+            }
+            sizes
+        })
+    }
+
+    fn size(&self, node_id: NodeId) -> usize {
+        self.sizes()[node_id]
+    }
+
+    // Picks files and directories, none inside another and never the root, whose deletion
+    // leaves at least `target_free_space` free on a disk of `disk_size` bytes.
+    fn plan_cleanup(&self,
+                    disk_size: usize,
+                    target_free_space: usize,
+                    objective: Objective) -> Result<CleanupPlan, String> {
+        let sizes = self.sizes();
+        let used = sizes[self.root()];
+        if used > disk_size {
+            return Err(format!("{} bytes are used on a disk of only {} bytes", used, disk_size));
+        }
+        let needed = (target_free_space + used).saturating_sub(disk_size);
+        if needed > used {
+            return Err(format!("even deleting everything frees only {} of the {} bytes needed", used, needed));
+        }
+        let (items, note) = match objective {
+            _ if needed == 0 => (Vec::new(), None),
+            Objective::Bytes => self.plan_fewest_bytes(sizes, needed),
+            Objective::Count => (self.plan_fewest_items(sizes, needed), None),
+        };
+        Ok(CleanupPlan {
+            freed: items.iter().map(|node_id| sizes[*node_id]).sum(),
+            items,
+            note,
+            used,
+            needed,
+            disk_size,
+        })
+    }
+
+    // Deleting a directory frees exactly what deleting all its files does, so the cheapest
+    // plan is a subset sum over the files. Directories are only used afterwards to replace
+    // all of their files. The search is bounded by the best single item; if it runs out of
+    // steps the best plan found so far is kept and the returned note says so.
+    fn plan_fewest_bytes(&self, sizes: &[usize], needed: usize) -> (Vec<NodeId>, Option<String>) {
+        let node_ids = self.node_ids();
+        let single = node_ids.iter()
+                             .filter(|node_id| **node_id != self.root() && sizes[**node_id] >= needed)
+                             .min_by_key(|node_id| sizes[**node_id])
+                             .copied();
+        if let Some(node_id) = single.filter(|node_id| sizes[*node_id] == needed) {
+            return (vec![node_id], None);
+        }
+
+        let mut files = node_ids.iter()
+                                .filter(|node_id| !self.nodes[**node_id].is_dir() && sizes[**node_id] > 0)
+                                .copied()
+                                .collect::<Vec<NodeId>>();
+        files.sort_by_key(|node_id| std::cmp::Reverse(sizes[*node_id]));
+        let mut search = SubsetSearch::new(files.iter().map(|node_id| sizes[*node_id]).collect(),
+                                           needed,
+                                           single.map(|node_id| sizes[node_id]).unwrap_or(sizes[self.root()] + 1));
+        search.run(0, 0);
+
+        let note = search.exhausted.then(|| {
+            format!("the search stopped after {} steps, so this plan may free more than needed",
+                    MAX_SEARCH_STEPS)
+        });
+        match search.best_chosen {
+            Some(best_chosen) => {
+                let mut chosen = vec![false; self.nodes.len()];
+                best_chosen.iter().for_each(|index| chosen[files[*index]] = true);
+                (self.collapse_into_directories(sizes, chosen), note)
+            },
+            None => (single.into_iter().collect(), note),
+        }
+    }
+
+    // Replaces directories whose files are all chosen by the directory itself.
+    fn collapse_into_directories(&self, sizes: &[usize], mut chosen: Vec<bool>) -> Vec<NodeId> {
+        let node_ids = self.node_ids();
+        for node_id in node_ids.iter().rev() {
+            let node = &self.nodes[*node_id];
+            if node.is_dir() && !node.children.is_empty() {
+                chosen[*node_id] = node.children.iter()
+                                       .all(|child_id| chosen[*child_id] || sizes[*child_id] == 0);
+            }
+        }
+        let mut items = Vec::new();
+        let mut stack = self.nodes[self.root()].children.clone();
+        while let Some(node_id) = stack.pop() {
+            if chosen[node_id] {
+                items.push(node_id);
+            } else {
+                stack.extend(self.nodes[node_id].children.iter());
+            }
+        }
+        items.sort_by_key(|node_id| std::cmp::Reverse(sizes[*node_id]));
+        items
+    }
+
+    // Any set of non-nested items frees at most what the same number of the largest
+    // top level items does, so taking the largest items that don't overlap what is already
+    // taken gives the fewest items. The last one is then swapped for the smallest item that
+    // still frees enough.
+    fn plan_fewest_items(&self, sizes: &[usize], needed: usize) -> Vec<NodeId> {
+        let mut candidates: Vec<NodeId> = self.node_ids()
+                                              .into_iter()
+                                              .filter(|node_id| *node_id != self.root() && sizes[*node_id] > 0)
+                                              .collect();
+        // Ancestors are at least as large as their descendants and come first on ties.
+        candidates.sort_by_key(|node_id| (std::cmp::Reverse(sizes[*node_id]), self.depth(*node_id)));
+
+        let mut items: Vec<NodeId> = Vec::new();
+        let mut freed = 0;
+        for node_id in candidates.iter() {
+            if freed >= needed {
+                break;
+            }
+            if !items.iter().any(|item| self.overlaps(*item, *node_id)) {
+                items.push(*node_id);
+                freed += sizes[*node_id];
+            }
+        }
+
+        if let Some(last) = items.pop() {
+            let rest = freed - sizes[last];
+            let replacement = candidates.iter()
+                                        .rev()
+                                        .find(|node_id| rest + sizes[**node_id] >= needed
+                                                        && !items.iter().any(|item| self.overlaps(*item, **node_id)))
+                                        .copied()
+                                        .unwrap_or(last);
+            items.push(replacement);
+        }
+        items.sort_by_key(|node_id| std::cmp::Reverse(sizes[*node_id]));
+        items
+    }
+
+    fn depth(&self, node_id: NodeId) -> usize {
+        std::iter::successors(self.nodes[node_id].parent, |id| self.nodes[*id].parent).count()
+    }
+
+    // Whether one of the two nodes contains the other.
+    fn overlaps(&self, left: NodeId, right: NodeId) -> bool {
+        let is_ancestor = |ancestor: NodeId, node_id: NodeId| {
+            std::iter::successors(Some(node_id), |id| self.nodes[*id].parent).any(|id| id == ancestor)
+        };
+        is_ancestor(left, right) || is_ancestor(right, left)
     }
 
-    fn solve2(&self) -> usize {
-        let needed_size: usize = 30000000;
-        let system_size: usize = 70000000;
-        let current_size: usize = system_size - self.nodes[self.root()].size(self);
+    fn describe_plan(&self, plan: &CleanupPlan) -> String {
+        let mut out = format!("{} of {} bytes are used, {} more bytes have to be freed\n",
+                              plan.used, plan.disk_size, plan.needed);
+        let sizes = self.sizes();
+        for node_id in plan.items.iter() {
+            out.push_str(format!("delete {} {} ({} bytes)\n",
+                                 if self.nodes[*node_id].is_dir() { "directory" } else { "file" },
+                                 self.path(*node_id),
+                                 sizes[*node_id]).as_str());
+        }
+        out.push_str(format!("{} items, {} bytes freed, {} bytes free afterwards\n",
+                             plan.items.len(),
+                             plan.freed,
+                             plan.disk_size - plan.used + plan.freed).as_str());
+        if let Some(note) = plan.note.as_ref() {
+            out.push_str(format!("note: {}\n", note).as_str());
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Objective {
+    Bytes,
+    Count,
+}
 
-        let mut candidates =
-            self.nodes.iter()
-                      .filter(|node| node.size.is_none())
-                      .map(|node| node.size(self)
-                      )
-                      .filter(|size| *size + current_size >= needed_size)
-                      .collect::<Vec<usize>>();
+// How many partial plans plan_fewest_bytes looks at before settling for the best one so far.
+const MAX_SEARCH_STEPS: usize = 1 << 24;
 
-        candidates.sort();
+// Branch and bound over file sizes sorted from largest to smallest: a branch is dropped
+// once it can no longer free enough, or can only free as much as the best plan found.
+struct SubsetSearch {
+    sizes: Vec<usize>,
+    // remaining[i] is the sum of sizes[i..].
+    remaining: Vec<usize>,
+    needed: usize,
+    best: usize,
+    best_chosen: Option<Vec<usize>>,
+    chosen: Vec<usize>,
+    steps: usize,
+    exhausted: bool,
+}
 
-        candidates[0] // guaranteed to exist, worse case we need to delete root.
+impl SubsetSearch {
+    fn new(sizes: Vec<usize>, needed: usize, bound: usize) -> Self {
+        let mut remaining = vec![0; sizes.len() + 1];
+        for i in (0..sizes.len()).rev() {
+            remaining[i] = remaining[i + 1] + sizes[i];
+        }
+        SubsetSearch {
+            sizes,
+            remaining,
+            needed,
+            best: bound,
+            best_chosen: None,
+            chosen: Vec::new(),
+            steps: 0,
+            exhausted: false,
+        }
     }
+
+    fn run(&mut self, index: usize, sum: usize) {
+        if self.steps == MAX_SEARCH_STEPS {
+            self.exhausted = true;
+            return;
+        }
+        self.steps += 1;
+        if sum >= self.needed {
+            if sum < self.best {
+                self.best = sum;
+                self.best_chosen = Some(self.chosen.clone());
+            }
+            return;
+        }
+        if index == self.sizes.len() || sum + self.remaining[index] < self.needed || self.best == self.needed {
+            return;
+        }
+        let size = self.sizes[index];
+        if sum + size < self.best {
+            self.chosen.push(index);
+            self.run(index + 1, sum + size);
+            self.chosen.pop();
+        }
+        // Leaving out a file only makes sense if all files of the same size are left out too,
+        // taking a later one instead would just repeat a plan already looked at.
+        let next = (index..self.sizes.len()).find(|i| self.sizes[*i] != size).unwrap_or(self.sizes.len());
+        self.run(next, sum);
+    }
+}
+
+struct CleanupPlan {
+    items: Vec<NodeId>,
+    // Why the plan may not be the best one.
+    note: Option<String>,
+    freed: usize,
+    used: usize,
+    needed: usize,
+    disk_size: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -180,16 +856,74 @@ impl Node {
         }
     }
 
-    fn size(&self, node_manager: &NodeManager)  -> usize {
-        if self.size.is_some() {
-            self.size.unwrap()
-        } else {
-            self.children.iter()
-                .map(|child_id|node_manager.nodes
-                    .get(*child_id)
-                    .unwrap()
-                    .size(node_manager))
-                .fold(0, |sum, val| sum + val)
+    fn is_dir(&self) -> bool {
+        self.size.is_none()
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(format!("\\u{:04x}", c as u32).as_str()),
+            c => out.push(c),
         }
     }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(lines: &[&str]) -> NodeManager {
+        let mut node_manager = NodeManager::new();
+        let interpreter = Interpreter::new();
+        for line in lines {
+            interpreter.run_line(&mut node_manager, line).unwrap();
+        }
+        node_manager
+    }
+
+    const TEST: &[&str] = &[
+        "$ cd /", "$ ls", "dir a", "14848514 b.txt", "8504156 c.dat", "dir d",
+        "$ cd a", "$ ls", "dir e", "29116 f", "2557 g", "62596 h.lst",
+        "$ cd e", "$ ls", "584 i",
+        "$ cd ..", "$ cd ..", "$ cd d", "$ ls", "4060174 j", "8033020 d.log", "5626152 d.ext", "7214296 k",
+    ];
+
+    #[test]
+    fn fewest_bytes_takes_an_item_of_exactly_the_needed_size() {
+        let node_manager = load(TEST);
+        let plan = node_manager.plan_cleanup(48381165, 584, Objective::Bytes).unwrap();
+        assert_eq!(plan.needed, 584);
+        assert_eq!(plan.freed, 584);
+        assert_eq!(plan.items.len(), 1);
+        assert!(plan.note.is_none());
+    }
+
+    #[test]
+    fn fewest_bytes_is_exact_on_the_example() {
+        let node_manager = load(TEST);
+        let plan = node_manager.plan_cleanup(70000000, 30000000, Objective::Bytes).unwrap();
+        assert_eq!(plan.freed, 8504156);
+        assert!(plan.note.is_none());
+        let plan = node_manager.plan_cleanup(70000000, 21700000, Objective::Bytes).unwrap();
+        assert_eq!(plan.needed, 81165);
+        assert_eq!(plan.freed, 29116 + 62596);
+    }
+
+    #[test]
+    fn cd_and_mkdir_refuse_files() {
+        let mut node_manager = load(TEST);
+        let interpreter = Interpreter::new();
+        assert_eq!(interpreter.run_line(&mut node_manager, "$ cd /b.txt"),
+                   Err("/b.txt is not a directory".to_string()));
+        assert_eq!(interpreter.run_line(&mut node_manager, "$ mkdir /a/f"),
+                   Err("/a/f is not a directory".to_string()));
+        assert!(interpreter.run_line(&mut node_manager, "$ mkdir /a/e").is_ok());
+    }
 }
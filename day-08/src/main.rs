@@ -1,36 +1,109 @@
-use std::collections::HashSet;
 use std::fs::File;
-use std::io::{self, BufReader, BufRead};
+use std::io::{self, BufReader, BufRead, BufWriter, Write};
 use std::env;
 
+// The first four are the puzzle's lines of sight, the rest are only used with --diagonals.
+const DIRECTIONS: [(isize, isize); 8] = [(-1, 0), (1, 0), (0, -1), (0, 1),
+                                         (-1, -1), (1, -1), (-1, 1), (1, 1)];
+const SIDES: [&str; 8] = ["L", "R", "T", "B", "TL", "TR", "BL", "BR"];
+
+// A product of up to eight viewing distances, saturating in the unlikely case it still
+// doesn't fit.
+type Score = u128;
+
 fn main() ->  io::Result<()> {
     let args: Vec<String> = env::args().collect();
-    let mut tree_map = read_input(&args[1])?;
+    let tree_map = read_input(&args[1])?;
+    let directions = if args.iter().skip(2).any(|x| x == "--diagonals") { 8 } else { 4 };
+    let survey = tree_map.survey(&DIRECTIONS[..directions]);
 
-    println!("{:?} is how many trees are visible from outside the grid", tree_map.solve1());
+    let mut options = args.iter().skip(2);
+    while let Some(option) = options.next() {
+        let mut value = || options.next()
+                                  .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+                                                                format!("{} expects a value", option)));
+        match option.as_str() {
+            "--diagonals" => {},
+            "--scores" => survey.scenic_scores.iter().for_each(|row| {
+                println!("{}", row.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(" "))
+            }),
+            "--top" => {
+                let k = value()?.parse::<usize>()
+                                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                for (rank, (point, score)) in survey.top_trees(k).iter().enumerate() {
+                    println!("#{} tree at x={} y={} with height {} has scenic score {}",
+                             rank + 1, point.x, point.y, tree_map.trees[point.y][point.x], score);
+                }
+            },
+            "--csv" => survey.write_csv(&tree_map, File::create(value()?)?)?,
+            "--ppm-scores" => write_ppm(File::create(value()?)?, &survey.scenic_scores)?,
+            "--ppm-visibility" => write_ppm(File::create(value()?)?, &survey.visible_side_counts())?,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                           format!("unknown option {}", option))),
+        }
+    }
 
-    println!("{:?} is the highest scenic score possible for any tree", tree_map.solve2());
+    println!("{:?} is how many trees are visible from outside the grid", survey.visible_tree_count());
+
+    println!("{:?} is the highest scenic score possible for any tree", survey.highest_scenic_score());
     Ok(())
 }
 
+// Rows are either packed single digits, as in the puzzle, or heights of any size separated
+// by commas and/or whitespace. The format is picked once for the whole file: a separator
+// anywhere, or lines of different lengths that can't be packed rows, mean heights are
+// separated on every row, so a one tree wide map reads each line as one height. Every
+// row has to be as wide as the first one.
 fn read_input(filename: &String) -> io::Result<TreeMap> {
     let file_in = File::open(filename)?;
-    let trees =
-        BufReader::new(file_in).lines()
-                                     .map(|line|line.unwrap()
-                                                                 .chars()
-                                                                 .map(|c|c.to_digit(10)
-                                                                                .unwrap())
-                                                                                .collect::<Vec<u32>>())
-                                     .collect::<Vec<Vec<u32>>>();
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (line_number, line) in BufReader::new(file_in).lines().enumerate() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            lines.push((line_number + 1, line.trim().to_string()));
+        }
+    }
+    let separated = lines.iter().any(|(_, line)| line.contains(is_separator))
+                    || lines.iter().any(|(_, line)| line.len() != lines[0].1.len());
+
+    let mut trees: Vec<Vec<u32>> = Vec::new();
+    for (line_number, line) in lines.iter() {
+        let row = parse_row(line, separated).map_err(|e| input_error(*line_number, e))?;
+        if let Some(first) = trees.first() {
+            if first.len() != row.len() {
+                return Err(input_error(*line_number,
+                                       format!("row has {} trees but the first row has {}", row.len(), first.len())));
+            }
+        }
+        trees.push(row);
+    }
     Ok(TreeMap::new(trees))
 }
 
+fn is_separator(c: char) -> bool {
+    c == ',' || c.is_whitespace()
+}
+
+fn parse_row(line: &str, separated: bool) -> Result<Vec<u32>, String> {
+    if separated {
+        line.split(is_separator)
+            .filter(|x| !x.is_empty())
+            .map(|x| x.parse::<u32>().map_err(|e| format!("invalid height {:?}: {}", x, e)))
+            .collect()
+    } else {
+        line.chars()
+            .map(|c| c.to_digit(10).ok_or_else(|| format!("invalid height {:?}", c)))
+            .collect()
+    }
+}
+
+fn input_error(line_number: usize, message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line_number, message))
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 struct TreeMap {
     trees: Vec<Vec<u32>>,
-    visible_trees: HashSet<Point>,
-    p_heighest: Option<u32>,
 }
 
 impl TreeMap {
@@ -38,110 +111,151 @@ impl TreeMap {
     fn new(trees: Vec<Vec<u32>>) -> Self {
         TreeMap {
             trees,
-            visible_trees: HashSet::new(),
-            p_heighest: None,
         }
     }
 
-    fn do_compare(&mut self,
-                  point: Point)  {
-
-        if self.p_heighest.is_none()
-            || self.p_heighest
-                   .unwrap() < self.trees[point.y][point.x] {
-            self.p_heighest = Some(self.trees[point.y][point.x]);
-            self.visible_trees.insert(point);
+    // One sweep per viewing direction, each visiting every tree once with a monotonic
+    // stack, instead of walking outwards from every tree.
+    fn survey(&self, directions: &[(isize, isize)]) -> Survey {
+        let mut survey = Survey::new(self.len(), self.height());
+        for (side, direction) in directions.iter().enumerate() {
+            for start in self.line_starts(*direction) {
+                self.sweep(start, *direction, side, &mut survey);
+            }
         }
+        survey
     }
 
-    fn solve1(&mut self) -> usize {
-
-
-        for y in 0..self.height() {
+    // Trees at the edge the viewer looks towards; a sweep walks back from there.
+    fn line_starts(&self, (dx, dy): (isize, isize)) -> Vec<Point> {
+        (0..self.height()).flat_map(|y| (0..self.len()).map(move |x| Point::new(x, y)))
+                          .filter(|point| self.step(point, (dx, dy)).is_none())
+                          .collect()
+    }
 
-            self.p_heighest = None;
-            for x in 0..self.len() {
-                self.do_compare(Point::new(x, y))
-            }
+    fn step(&self, point: &Point, (dx, dy): (isize, isize)) -> Option<Point> {
+        let x = point.x.checked_add_signed(dx)?;
+        let y = point.y.checked_add_signed(dy)?;
+        if x < self.len() && y < self.height() {
+            Some(Point::new(x, y))
+        } else {
+            None
+        }
+    }
 
-            self.p_heighest = None;
-            for x in  (0..self.len()).rev() {
-                self.do_compare(Point::new(x,y))
+    fn sweep(&self, start: Point, direction: (isize, isize), side: usize, survey: &mut Survey) {
+        // Trees seen so far on this line, never increasing in height from bottom to top.
+        let mut stack: Vec<(u32, usize)> = Vec::new();
+        let mut current = Some(start);
+        let mut distance_from_edge: usize = 0;
+        while let Some(point) = current {
+            let height = self.trees[point.y][point.x];
+            while stack.last().is_some_and(|(other, _)| *other < height) {
+                stack.pop();
             }
-        };
+            let viewing_distance = match stack.last() {
+                Some((_, blocker)) => distance_from_edge - blocker,
+                None => {
+                    survey.visible_from[point.y][point.x] |= 1 << side;
+                    distance_from_edge
+                },
+            };
+            let score = &mut survey.scenic_scores[point.y][point.x];
+            *score = score.saturating_mul(viewing_distance as Score);
+            stack.push((height, distance_from_edge));
+            distance_from_edge += 1;
+            current = self.step(&point, (-direction.0, -direction.1));
+        }
+    }
 
-        for x in (0..self.len()).rev() {
+    fn len(&self) -> usize {
+        self.trees.first().map_or(0, |row| row.len())
+    }
 
-            self.p_heighest = None;
-            for y in 0..self.height() {
-                self.do_compare(Point::new(x,y))
-            }
+    fn height(&self) -> usize {
+        self.trees.len()
+    }
+}
 
-            self.p_heighest = None;
-            for y in  (0..self.height()).rev() {
-                self.do_compare(Point::new(x,y))
-            }
-        };
+struct Survey {
+    // Bit i is set when the tree can be seen from outside the grid looking along DIRECTIONS[i].
+    visible_from: Vec<Vec<u8>>,
+    scenic_scores: Vec<Vec<Score>>,
+}
 
-        self.visible_trees
-            .len()
+impl Survey {
+    fn new(len: usize, height: usize) -> Self {
+        Survey {
+            visible_from: vec![vec![0; len]; height],
+            scenic_scores: vec![vec![1; len]; height],
+        }
     }
 
-    fn solve2(&self) -> usize {
-        // Trees at border have automatic viewing scores of 0 due to ..
-        (1..self.height() - 1).map(|y|{
-            (1..self.len() - 1).map(|x| self.visible_trees_from_point(Point::new(x,y)))
-                           .max()
-                           .unwrap()
-
-        }).max()
-          .unwrap()
+    fn visible_tree_count(&self) -> usize {
+        self.visible_from.iter().flatten().filter(|sides| **sides != 0).count()
     }
 
-    fn visible_trees_from_point(&self, point: Point) -> usize {
-        let mut visible_trees_to_the_left: usize = 0;
-        for x in (0..point.x).rev() {
+    fn highest_scenic_score(&self) -> Score {
+        self.scenic_scores.iter().flatten().copied().max().unwrap_or(0)
+    }
 
-            visible_trees_to_the_left += 1;
-            if self.trees[point.y][point.x] <= self.trees[point.y][x] {
-                break;
-            }
-        }
+    fn visible_side_counts(&self) -> Vec<Vec<Score>> {
+        self.visible_from.iter()
+            .map(|row| row.iter().map(|sides| sides.count_ones() as Score).collect())
+            .collect()
+    }
 
-        let mut visible_trees_to_the_right: usize = 0;
-        for x in (point.x + 1)..self.len() {
-            visible_trees_to_the_right += 1;
-            if self.trees[point.y][point.x] <= self.trees[point.y][x] {
-                break;
-            }
-        }
+    fn visible_sides(&self, point: &Point) -> String {
+        SIDES.iter()
+             .enumerate()
+             .filter(|(side, _)| self.visible_from[point.y][point.x] & (1 << side) != 0)
+             .map(|(_, name)| *name)
+             .collect::<Vec<&str>>()
+             .join("+")
+    }
 
-        let mut visible_trees_above: usize = 0;
-        for y in (0..point.y).rev() {
-            visible_trees_above += 1;
-            if self.trees[point.y][point.x] <= self.trees[y][point.x] {
-                break;
-            }
-        }
+    // The k highest scores, ties broken by position in reading order.
+    fn top_trees(&self, k: usize) -> Vec<(Point, Score)> {
+        let mut trees = self.scenic_scores.iter()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, score)| (Point::new(x, y), *score)))
+            .collect::<Vec<(Point, Score)>>();
+        trees.sort_by(|(l, l_score), (r, r_score)| {
+            r_score.cmp(l_score).then(l.y.cmp(&r.y)).then(l.x.cmp(&r.x))
+        });
+        trees.truncate(k);
+        trees
+    }
 
-        let mut visible_trees_below: usize = 0;
-        for y in (point.y + 1)..self.height() {
-            visible_trees_below += 1;
-            if self.trees[point.y][point.x] <= self.trees[y][point.x] {
-                break;
+    fn write_csv<W: Write>(&self, tree_map: &TreeMap, out: W) -> io::Result<()> {
+        let mut out = BufWriter::new(out);
+        writeln!(out, "x,y,height,visible_from,scenic_score")?;
+        for (y, row) in self.scenic_scores.iter().enumerate() {
+            for (x, score) in row.iter().enumerate() {
+                let point = Point::new(x, y);
+                writeln!(out, "{},{},{},{},{}",
+                         x, y, tree_map.trees[y][x], self.visible_sides(&point), score)?;
             }
         }
-
-        visible_trees_to_the_left * visible_trees_to_the_right * visible_trees_above * visible_trees_below
-    }
-
-    fn len(&self) -> usize {
-        self.trees.get(0).unwrap().len()
+        out.flush()
     }
+}
 
-    fn height(&self) -> usize {
-        self.trees.len()
+// Binary PPM with every value scaled to a grey level, the maximum being white.
+fn write_ppm<W: Write>(out: W, values: &[Vec<Score>]) -> io::Result<()> {
+    let mut out = BufWriter::new(out);
+    let height = values.len();
+    let width = values.first().map_or(0, |row| row.len());
+    let max = values.iter().flatten().copied().max().unwrap_or(0).max(1);
+    write!(out, "P6\n{} {}\n255\n", width, height)?;
+    for value in values.iter().flatten() {
+        let grey = match value.checked_mul(255) {
+            Some(scaled) => scaled / max,
+            None => value / (max / 255),
+        } as u8;
+        out.write_all(&[grey, grey, grey])?;
     }
+    out.flush()
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -1,69 +1,231 @@
 use std::collections::HashSet;
 use std::fs::File;
-use std::io::{self, BufReader, BufRead};
+use std::io::{self, BufReader, BufRead, BufWriter, Write};
+use std::path::Path;
 use std::env;
 
 fn main() ->  io::Result<()> {
     let args: Vec<String> = env::args().collect();
     let rope_movements = read_input(&args[1])?;
 
-    println!("{:?} positions does the tail of the rope visit at least once.",
-             solve1(&rope_movements));
+    let mut knots: Option<usize> = None;
+    let mut trajectory_file: Option<&String> = None;
+    let mut render = false;
+    let mut frames: Option<(&String, usize, usize)> = None;
+    let mut rules: Vec<Box<dyn FollowRule>> = Vec::new();
+    let mut compare = false;
+    let mut options = args.iter().skip(2);
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--trajectory" => trajectory_file = Some(option_value(option, options.next())?),
+            "--render" => render = true,
+            "--rule" => rules.push(rule_from_name(option_value(option, options.next())?)?),
+            "--compare" => compare = true,
+            "--ppm-frames" => {
+                let directory = option_value(option, options.next())?;
+                let first = parse_step(option, options.next())?;
+                let last = parse_step(option, options.next())?;
+                frames = Some((directory, first, last));
+            },
+            "--knots" => knots = Some(options.next()
+                                             .and_then(|x| x.parse::<usize>().ok())
+                                             .filter(|x| *x > 0)
+                                             .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+                                                                           "--knots expects a positive number"))?),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                           format!("unknown option {}", option))),
+        }
+    }
+
+    if compare {
+        if rules.is_empty() {
+            rules = vec![Box::new(StandardRule), Box::new(SlackRule::new(2)), Box::new(OrthogonalRule)];
+        }
+        print!("{}", compare_rules(&rope_movements, &rules,
+                                   &knots.map_or(vec![2, 10], |knots| vec![knots])));
+        return Ok(());
+    }
+    if rules.len() > 1 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                  "several rules can only be used with --compare"));
+    }
+    let rule = rules.pop().unwrap_or_else(|| Box::new(StandardRule));
+    let rule = rule.as_ref();
+
+    if trajectory_file.is_some() || render || frames.is_some() {
+        let trajectory = Trajectory::record(&rope_movements, knots.unwrap_or(10), rule);
+        if let Some(trajectory_file) = trajectory_file {
+            trajectory.write_csv(File::create(trajectory_file)?)?;
+        }
+        if render {
+            println!("{}", trajectory.render_rope(trajectory.steps() - 1));
+            println!();
+            println!("{}", trajectory.render_tail_visits());
+        }
+        if let Some((directory, first, last)) = frames {
+            trajectory.write_frames(Path::new(directory), first, last)?;
+        }
+        return Ok(());
+    }
 
-    println!("{:?} positions does the tail of the rope visit at least once.",
-             solve2(&rope_movements));
+    match knots {
+        Some(knots) => {
+            let rope = simulate(&rope_movements, knots, rule);
+            println!("{:?} positions does the tail of the rope visit at least once.",
+                     rope.tail_visits());
+            for (knot, visits) in rope.visit_counts().iter().enumerate() {
+                println!("knot {:?} visits {:?} positions", knot, visits);
+            }
+        },
+        None => {
+            println!("{:?} positions does the tail of the rope visit at least once.",
+                     simulate(&rope_movements, 2, rule).tail_visits());
+
+            println!("{:?} positions does the tail of the rope visit at least once.",
+                     simulate(&rope_movements, 10, rule).tail_visits());
+        },
+    }
 
     Ok(())
 }
 
-fn read_input(filename: &String) -> io::Result<Vec<RopeMovement>> {
-    let file_in = File::open(filename)?;
-    let rope_movements =
-        BufReader::new(file_in)
-                  .lines()
-                  .map(|line| line.unwrap())
-                  .map(|line| RopeMovement::new(line))
-                  .collect::<Vec<RopeMovement>>();
-    Ok(rope_movements)
+fn option_value<'a>(option: &str, value: Option<&'a String>) -> io::Result<&'a String> {
+    value.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+                                       format!("{} expects a value", option)))
 }
 
-fn solve1(rope_movements: &Vec<RopeMovement>) -> usize {
-    let mut visited_tail_positions: HashSet<Point> = HashSet::new();
-
-    let mut position_head = Point::new(0, 0);
-    let mut position_tail = Point::new(0,0);
+fn parse_step(option: &str, value: Option<&String>) -> io::Result<usize> {
+    option_value(option, value)?.parse::<usize>()
+                                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput,
+                                                            format!("{} expects step numbers: {}", option, e)))
+}
 
-    visited_tail_positions.insert(position_tail.clone());
+fn read_input(filename: &String) -> io::Result<Vec<RopeMovement>> {
+    let file_in = File::open(filename)?;
+    BufReader::new(file_in)
+              .lines()
+              .enumerate()
+              .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+              .map(|(line_number, line)| {
+                  RopeMovement::new(line?.as_str()).map_err(|e| {
+                      io::Error::new(io::ErrorKind::InvalidData,
+                                     format!("line {}: {}", line_number + 1, e))
+                  })
+              })
+              .collect()
+}
 
+fn simulate(rope_movements: &[RopeMovement], knots: usize, rule: &dyn FollowRule) -> Rope {
+    let mut rope = Rope::new(knots);
     for rope_movement in rope_movements {
         for _step in 0..(rope_movement.amount) {
-            position_head.move_step(&rope_movement.direction);
-            propagate_move(&mut position_head, &mut position_tail);
-            visited_tail_positions.insert(position_tail.clone());
+            rope.step(rope_movement.direction, rule);
         }
     }
+    rope
+}
+
+// Table of tail visit counts, one row per rule and one column per rope length.
+fn compare_rules(rope_movements: &[RopeMovement], rules: &[Box<dyn FollowRule>], knots: &[usize]) -> String {
+    let name_width = rules.iter().map(|rule| rule.name().len()).max().unwrap_or(0).max("rule".len());
+    let mut table = format!("{:<width$}", "rule", width = name_width);
+    for knots in knots {
+        table.push_str(format!(" | {:>9}", format!("{} knots", knots)).as_str());
+    }
+    table.push('\n');
+    for rule in rules {
+        table.push_str(format!("{:<width$}", rule.name(), width = name_width).as_str());
+        for knots in knots {
+            table.push_str(format!(" | {:>9}", simulate(rope_movements, *knots, rule.as_ref()).tail_visits()).as_str());
+        }
+        table.push('\n');
+    }
+    table
+}
+
+// Decides how a knot reacts after the knot in front of it moved.
+trait FollowRule {
+    fn name(&self) -> String;
+
+    fn follow(&self, leader: &Point, follower: &mut Point);
+}
+
+fn rule_from_name(name: &str) -> io::Result<Box<dyn FollowRule>> {
+    match name.split_once(':') {
+        None if name == "standard" => Ok(Box::new(StandardRule)),
+        None if name == "orthogonal" => Ok(Box::new(OrthogonalRule)),
+        Some(("slack", slack)) => match slack.parse::<i32>() {
+            Ok(slack) if slack > 0 => Ok(Box::new(SlackRule::new(slack))),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                    format!("{:?} is not a valid slack", slack))),
+        },
+        _ => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                format!("unknown rule {:?}, expected standard, slack:K or orthogonal", name))),
+    }
+}
+
+// The rule from the puzzle.
+struct StandardRule;
 
+impl FollowRule for StandardRule {
+    fn name(&self) -> String {
+        String::from("standard")
+    }
 
-    visited_tail_positions.len()
+    fn follow(&self, leader: &Point, follower: &mut Point) {
+        propagate_move(leader, follower)
+    }
 }
 
-fn solve2(rope_movements: &Vec<RopeMovement>) -> usize {
-    let mut visited_tail_positions: HashSet<Point> = HashSet::new();
-    let mut positions: Vec<Point> = vec![Point::new(0,0) ; 10];
+// The knot only moves once it is more than `slack` cells away, then steps straight or
+// diagonally towards the knot in front. A slack of 1 is the standard rule.
+struct SlackRule {
+    slack: i32,
+}
 
-    for rope_movement in rope_movements {
-        for _step in 0..(rope_movement.amount) {
-            positions.get_mut(0).unwrap().move_step(&rope_movement.direction);
-            for i in 1..positions.len() {
-                propagate_move(&(positions[i - 1].clone()),
-                               positions.get_mut(i).unwrap());
+impl SlackRule {
+    fn new(slack: i32) -> Self {
+        SlackRule {
+            slack
+        }
+    }
+}
 
-            }
-            visited_tail_positions.insert(positions.last().unwrap().clone());
+impl FollowRule for SlackRule {
+    fn name(&self) -> String {
+        format!("slack:{}", self.slack)
+    }
+
+    fn follow(&self, leader: &Point, follower: &mut Point) {
+        let dx = leader.x - follower.x;
+        let dy = leader.y - follower.y;
+        if dx.abs().max(dy.abs()) > self.slack {
+            follower.move_step((dx.signum(), dy.signum()));
         }
     }
+}
+
+// The knot never moves diagonally: once out of touch it steps along the axis on which it
+// is furthest behind, horizontally when both are equal.
+struct OrthogonalRule;
+
+impl FollowRule for OrthogonalRule {
+    fn name(&self) -> String {
+        String::from("orthogonal")
+    }
 
-    visited_tail_positions.len()
+    fn follow(&self, leader: &Point, follower: &mut Point) {
+        let dx = leader.x - follower.x;
+        let dy = leader.y - follower.y;
+        if dx.abs().max(dy.abs()) <= 1 {
+            return;
+        }
+        if dx.abs() >= dy.abs() {
+            follower.move_step((dx.signum(), 0));
+        } else {
+            follower.move_step((0, dy.signum()));
+        }
+    }
 }
 
 fn propagate_move(position_head: &Point, position_tail: &mut Point) {
@@ -109,26 +271,206 @@ fn propagate_move(position_head: &Point, position_tail: &mut Point) {
         }
     ;
 
-    if directions_tail.is_some() {
-        for direction in directions_tail.unwrap() {
-            position_tail.move_step(&direction)
+    if let Some(directions_tail) = directions_tail {
+        for direction in directions_tail {
+            position_tail.move_step(direction_offset(direction))
         }
     }
 }
 
+// Knot 0 is the head, the last knot the tail. Every knot remembers where it has been.
+#[derive(Debug, Clone)]
+struct Rope {
+    knots: Vec<Point>,
+    visited: Vec<HashSet<Point>>,
+}
+
+impl Rope {
+    fn new(knots: usize) -> Self {
+        Rope {
+            knots: vec![Point::new(0, 0); knots],
+            visited: vec![HashSet::from([Point::new(0, 0)]); knots],
+        }
+    }
+
+    fn step(&mut self, direction: (i32, i32), rule: &dyn FollowRule) {
+        self.knots[0].move_step(direction);
+        for i in 1..self.knots.len() {
+            let (leading, following) = self.knots.split_at_mut(i);
+            rule.follow(&leading[i - 1], &mut following[0]);
+        }
+        for (knot, visited) in self.knots.iter().zip(self.visited.iter_mut()) {
+            visited.insert(knot.clone());
+        }
+    }
+
+    fn tail_visits(&self) -> usize {
+        self.visited.last().map_or(0, |visited| visited.len())
+    }
+
+    fn visit_counts(&self) -> Vec<usize> {
+        self.visited.iter().map(|visited| visited.len()).collect()
+    }
+}
+
+// The position of every knot after every step, step 0 being the starting position.
+struct Trajectory {
+    steps: Vec<Vec<Point>>,
+}
+
+impl Trajectory {
+    fn record(rope_movements: &[RopeMovement], knots: usize, rule: &dyn FollowRule) -> Self {
+        let mut rope = Rope::new(knots);
+        let mut steps = vec![rope.knots.clone()];
+        for rope_movement in rope_movements {
+            for _step in 0..(rope_movement.amount) {
+                rope.step(rope_movement.direction, rule);
+                steps.push(rope.knots.clone());
+            }
+        }
+        Trajectory {
+            steps,
+        }
+    }
+
+    fn steps(&self) -> usize {
+        self.steps.len()
+    }
+
+    fn write_csv<W: Write>(&self, out: W) -> io::Result<()> {
+        let mut out = BufWriter::new(out);
+        writeln!(out, "step,knot,x,y")?;
+        for (step, knots) in self.steps.iter().enumerate() {
+            for (knot, point) in knots.iter().enumerate() {
+                writeln!(out, "{},{},{},{}", step, knot, point.x, point.y)?;
+            }
+        }
+        out.flush()
+    }
+
+    // Smallest area holding every position of every knot, so all frames line up.
+    fn bounds(&self) -> (Point, Point) {
+        let points = self.steps.iter().flatten();
+        (Point::new(points.clone().map(|p| p.x).min().unwrap_or(0), points.clone().map(|p| p.y).min().unwrap_or(0)),
+         Point::new(points.clone().map(|p| p.x).max().unwrap_or(0), points.map(|p| p.y).max().unwrap_or(0)))
+    }
+
+    fn tail_visits_until(&self, step: usize) -> HashSet<Point> {
+        self.steps.iter()
+                  .take(step + 1)
+                  .filter_map(|knots| knots.last().cloned())
+                  .collect()
+    }
+
+    // Draws cells from top to bottom, `cell` picks the character for each position.
+    fn render<F: Fn(&Point) -> char>(&self, cell: F) -> String {
+        let (min, max) = self.bounds();
+        (min.y..=max.y).rev()
+                       .map(|y| (min.x..=max.x).map(|x| cell(&Point::new(x, y))).collect::<String>())
+                       .collect::<Vec<String>>()
+                       .join("\n")
+    }
+
+    // The rope as drawn in the puzzle: H for the head, numbers for the knots in between
+    // and T for the tail. Knots closer to the head cover the ones behind them.
+    fn render_rope(&self, step: usize) -> String {
+        let knots = &self.steps[step];
+        self.render(|point| {
+            match knots.iter().position(|knot| knot == point) {
+                Some(0) => 'H',
+                Some(knot) if knot + 1 == knots.len() => 'T',
+                Some(knot) => char::from_digit((knot % 36) as u32, 36).unwrap_or('?'),
+                None if *point == Point::new(0, 0) => 's',
+                None => '.',
+            }
+        })
+    }
+
+    fn render_tail_visits(&self) -> String {
+        let visited = self.tail_visits_until(self.steps() - 1);
+        self.render(|point| {
+            if *point == Point::new(0, 0) {
+                's'
+            } else if visited.contains(point) {
+                '#'
+            } else {
+                '.'
+            }
+        })
+    }
+
+    // One PPM per step in first..=last: visited tail cells grey, knots white, the head red.
+    fn write_frames(&self, directory: &Path, first: usize, last: usize) -> io::Result<()> {
+        const SCALE: usize = 4;
+        let last = last.min(self.steps() - 1);
+        let (min, max) = self.bounds();
+        let width = (max.x - min.x + 1) as usize;
+        let height = (max.y - min.y + 1) as usize;
+        for step in first..=last {
+            let visited = self.tail_visits_until(step);
+            let knots = &self.steps[step];
+            let mut out = BufWriter::new(File::create(directory.join(format!("frame-{:06}.ppm", step)))?);
+            write!(out, "P6\n{} {}\n255\n", width * SCALE, height * SCALE)?;
+            for row in 0..height * SCALE {
+                for column in 0..width * SCALE {
+                    let point = Point::new(min.x + (column / SCALE) as i32, max.y - (row / SCALE) as i32);
+                    let colour: [u8; 3] = match knots.iter().position(|knot| *knot == point) {
+                        Some(0) => [255, 0, 0],
+                        Some(_) => [255, 255, 255],
+                        None if visited.contains(&point) => [96, 96, 96],
+                        None => [0, 0, 0],
+                    };
+                    out.write_all(&colour)?;
+                }
+            }
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 struct RopeMovement {
-    direction: char,
+    direction: (i32, i32),
     amount: u32,
 }
 
 impl RopeMovement {
-    fn new(line: String) -> Self {
-        let mut split = line.split(" ");
-        RopeMovement {
-            direction: split.next().unwrap().chars().next().unwrap(),
-            amount: split.next().unwrap().parse::<u32>().unwrap()
-        }
+    fn new(line: &str) -> Result<Self, String> {
+        let mut split = line.split_whitespace();
+        let (direction, amount) = match (split.next(), split.next(), split.next()) {
+            (Some(direction), Some(amount), None) => (direction, amount),
+            _ => return Err(format!("expected a direction and an amount but found {:?}", line)),
+        };
+        Ok(RopeMovement {
+            direction: parse_direction(direction)?,
+            amount: amount.parse::<u32>().map_err(|e| format!("invalid amount {:?}: {}", amount, e))?,
+        })
+    }
+}
+
+// Straight moves U, D, L and R, or a vertical and horizontal one combined such as UL.
+fn parse_direction(direction: &str) -> Result<(i32, i32), String> {
+    let offsets = direction.chars()
+                           .map(|c| match c {
+                               'U' | 'D' | 'L' | 'R' => Ok(direction_offset(c)),
+                               _ => Err(format!("unknown direction {:?}", direction)),
+                           })
+                           .collect::<Result<Vec<(i32, i32)>, String>>()?;
+    match offsets.as_slice() {
+        [offset] => Ok(*offset),
+        [(0, dy), (dx, 0)] | [(dx, 0), (0, dy)] if *dx != 0 && *dy != 0 => Ok((*dx, *dy)),
+        _ => Err(format!("unknown direction {:?}", direction)),
+    }
+}
+
+fn direction_offset(direction: char) -> (i32, i32) {
+    match direction {
+        'U' => (0, 1),
+        'D' => (0, -1),
+        'L' => (-1, 0),
+        'R' => (1, 0),
+        _ => panic!("Unknown direction")
     }
 }
 
@@ -146,22 +488,8 @@ impl Point {
         }
     }
 
-    fn move_step(&mut self, direction: &char) {
-        match direction {
-            'U' => {
-                self.y = self.y + 1
-            }
-            'D' => {
-                self.y = self.y - 1
-            }
-            'L' => {
-                self.x = self.x - 1
-            }
-            'R' => {
-                self.x = self.x + 1
-            }
-            _ => panic!("Unknown direction")
-        }
+    fn move_step(&mut self, (dx, dy): (i32, i32)) {
+        self.x += dx;
+        self.y += dy;
     }
 }
-
@@ -1,134 +1,745 @@
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{self, BufReader, BufRead};
+use std::io::{self, BufReader, BufRead, BufWriter, Write};
 use std::env;
-use crate::Instruction::{Addx, Noop};
+
+const DEFAULT_SAMPLES: [u64; 6] = [20, 60, 100, 140, 180, 220];
+const DEFAULT_MAX_CYCLES: u64 = 100_000;
 
 fn main() ->  io::Result<()> {
     let args: Vec<String> = env::args().collect();
-    let mut video_system =  read_input(&args[1])?;
+    let mut crt = Crt::default();
+    let mut format = InputFormat::Program;
+    let mut output: Option<String> = None;
+    let mut samples: Vec<u64> = DEFAULT_SAMPLES.to_vec();
+    let mut breakpoints: Vec<Breakpoint> = Vec::new();
+    let mut trace: Option<String> = None;
+    let mut max_cycles = DEFAULT_MAX_CYCLES;
+    let mut options = args.iter().skip(2);
+    while let Some(option) = options.next() {
+        let mut value = || options.next()
+                                  .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+                                                                format!("{} expects a value", option)));
+        match option.as_str() {
+            "--samples" => samples = value()?.split(',')
+                                             .map(|x| parse_number(x.trim()))
+                                             .collect::<io::Result<Vec<u64>>>()?,
+            "--width" => crt.width = parse_number(value()?)?,
+            "--height" => crt.height = parse_number(value()?)?,
+            "--sprite" => crt.sprite_width = parse_number(value()?)?,
+            "--assemble" => format = InputFormat::Assembly,
+            "--draw" => format = InputFormat::Image,
+            "--output" => output = Some(value()?.clone()),
+            "--trace" => trace = Some(value()?.clone()),
+            "--break" => breakpoints.push(Breakpoint::parse(value()?)
+                                              .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?),
+            "--max-cycles" => max_cycles = parse_number(value()?)?,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                           format!("unknown option {}", option))),
+        }
+    }
+
+    let mut video_system = read_input(&args[1], format, crt)?;
+    if let Some(filename) = output {
+        write_program(File::create(filename)?, &video_system.cpu.program)?;
+    }
+    if let Some(filename) = trace {
+        video_system.clone().write_trace(File::create(filename)?, max_cycles)?;
+    }
+    if !breakpoints.is_empty() {
+        video_system.clone().debug(&breakpoints, max_cycles);
+    }
 
-    println!("{:?} is the sum of these six signal strengths.",
-             video_system.clone().solve1());
+    println!("{:?} is the sum of the signal strengths during cycles {}.",
+             video_system.clone().solve1(&samples),
+             samples.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(", "));
 
-    video_system.solve2();
+    let screen = video_system.solve2();
+    print!("{}", screen);
+    match screen.read_letters() {
+        Ok(letters) => println!("{} are the capital letters shown on the CRT.", letters),
+        Err(unknown) => println!("The CRT shows no readable letters, {}", unknown),
+    }
 
     Ok(())
 }
 
-fn read_input(filename: &String) -> io::Result<VideoSystem> {
+fn parse_number<T: std::str::FromStr>(text: &str) -> io::Result<T> where T::Err: std::fmt::Display {
+    text.parse::<T>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid number {:?}: {}", text, e)))
+}
+
+enum InputFormat {
+    Program,
+    // Source for the assembler, see assemble().
+    Assembly,
+    // A picture of '#' and '.' the generated program has to draw on the CRT.
+    Image,
+}
+
+fn read_input(filename: &String, format: InputFormat, mut crt: Crt) -> io::Result<VideoSystem> {
     let file_in = File::open(filename)?;
-    let instructions =
-        BufReader::new(file_in)
-            .lines()
-            .map(|line| line.unwrap())
-            .map(|line| {
-                if line.contains("noop") {
-                    Noop { cycle_length: 0 }
-                } else {
-                    let mut it = line.split(" ");
-                    it.next();
-                    Addx {
-                        cycle_length: 1,
-                        argument: it.next().unwrap().parse::<i64>().unwrap()
-                    }
+    let lines = BufReader::new(file_in)
+                    .lines()
+                    .collect::<io::Result<Vec<String>>>()?;
+    let program = match format {
+        InputFormat::Program => parse_program(&lines),
+        InputFormat::Assembly => assemble(&lines),
+        InputFormat::Image => Screen::parse(&lines).and_then(|target| {
+            crt.width = target.width;
+            crt.height = target.height;
+            draw_program(&target, &crt)
+        }),
+    }.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(VideoSystem::new(program, crt))
+}
+
+fn write_program<W: Write>(out: W, program: &[Instruction]) -> io::Result<()> {
+    let mut out = BufWriter::new(out);
+    for instruction in program.iter() {
+        writeln!(out, "{}", instruction)?;
+    }
+    out.flush()
+}
+
+fn parse_program(lines: &[String]) -> Result<Vec<Instruction>, String> {
+    lines.iter()
+         .enumerate()
+         .filter(|(_, line)| !line.trim().is_empty())
+         .map(|(line_number, line)| {
+             Instruction::parse(line).map_err(|e| format!("line {}: {}", line_number + 1, e))
+         })
+         .collect()
+}
+
+const MAX_MACRO_DEPTH: usize = 16;
+
+struct Macro {
+    parameters: Vec<String>,
+    body: Vec<String>,
+}
+
+// Assembly source is the program format plus
+//   ; comment             up to the end of the line
+//   name: instruction     labels an instruction, jmp and jnz may use the label as offset
+//   .macro name p q       starts a macro, $p and $q in its body are replaced by the
+//   ...                   arguments of every use "name 1 x"
+//   .endm
+// Labels have to be unique, so a macro containing one can only be used once.
+fn assemble(lines: &[String]) -> Result<Vec<Instruction>, String> {
+    let mut macros: HashMap<String, Macro> = HashMap::new();
+    let mut statements: Vec<(usize, String)> = Vec::new();
+    let mut definition: Option<(usize, String, Macro)> = None;
+    for (line_number, line) in lines.iter().enumerate().map(|(i, line)| (i + 1, line)) {
+        let text = line.split(';').next().unwrap_or("").trim();
+        let mut words = text.split_whitespace();
+        match (words.next(), definition.as_mut()) {
+            (Some(".macro"), None) => {
+                let name = words.next()
+                                .ok_or_else(|| format!("line {}: .macro needs a name", line_number))?;
+                definition = Some((line_number, name.to_string(), Macro {
+                    parameters: words.map(String::from).collect(),
+                    body: Vec::new(),
+                }));
+            },
+            (Some(".macro"), Some(_)) => return Err(format!("line {}: macros cannot be nested", line_number)),
+            (Some(".endm"), Some(_)) => {
+                if let Some((_, name, body)) = definition.take() {
+                    macros.insert(name, body);
                 }
-            })
-            .collect::<Vec<Instruction>>();
-    Ok(VideoSystem::new(instructions))
+            },
+            (Some(".endm"), None) => return Err(format!("line {}: .endm without .macro", line_number)),
+            (_, Some((_, _, body))) => body.body.push(text.to_string()),
+            (_, None) => statements.push((line_number, text.to_string())),
+        }
+    }
+    if let Some((line_number, name, _)) = definition {
+        return Err(format!("line {}: macro {} is missing its .endm", line_number, name));
+    }
+
+    let mut assembler = Assembler {
+        macros,
+        labels: HashMap::new(),
+        instructions: Vec::new(),
+    };
+    for (line_number, text) in statements.iter() {
+        assembler.expand(*line_number, text, 0)?;
+    }
+    assembler.instructions
+             .iter()
+             .enumerate()
+             .map(|(index, (line_number, words))| {
+                 let resolved = words.iter()
+                                     .enumerate()
+                                     .map(|(i, word)| match assembler.labels.get(word) {
+                                         Some(target) if i > 0 => (*target as i64 - index as i64).to_string(),
+                                         _ => word.clone(),
+                                     })
+                                     .collect::<Vec<String>>()
+                                     .join(" ");
+                 Instruction::parse(&resolved).map_err(|e| format!("line {}: {}", line_number, e))
+             })
+             .collect()
 }
 
+struct Assembler {
+    macros: HashMap<String, Macro>,
+    // Index of the instruction every label points at.
+    labels: HashMap<String, usize>,
+    // Expanded instructions with the source line they come from.
+    instructions: Vec<(usize, Vec<String>)>,
+}
+
+impl Assembler {
+    fn expand(&mut self, line_number: usize, text: &str, depth: usize) -> Result<(), String> {
+        let mut text = text;
+        while let Some((label, rest)) = text.split_once(':') {
+            self.define_label(line_number, label.trim())?;
+            text = rest.trim();
+        }
+        let words: Vec<String> = text.split_whitespace().map(String::from).collect();
+        let Some(name) = words.first() else {
+            return Ok(());
+        };
+        let Some(definition) = self.macros.get(name) else {
+            self.instructions.push((line_number, words));
+            return Ok(());
+        };
+        if depth >= MAX_MACRO_DEPTH {
+            return Err(format!("line {}: macros nest deeper than {} levels", line_number, MAX_MACRO_DEPTH));
+        }
+        if definition.parameters.len() != words.len() - 1 {
+            return Err(format!("line {}: macro {} expects {} arguments but got {}",
+                               line_number, name, definition.parameters.len(), words.len() - 1));
+        }
+        // Longest names first, so $n does not replace the start of $n2.
+        let mut arguments: Vec<(&String, &String)> = definition.parameters.iter().zip(words.iter().skip(1)).collect();
+        arguments.sort_by_key(|(parameter, _)| std::cmp::Reverse(parameter.len()));
+        let body: Vec<String> = definition.body
+                                          .iter()
+                                          .map(|line| arguments.iter().fold(line.clone(), |line, (parameter, argument)| {
+                                              line.replace(&format!("${}", parameter), argument)
+                                          }))
+                                          .collect();
+        for line in body.iter() {
+            self.expand(line_number, line, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    fn define_label(&mut self, line_number: usize, label: &str) -> Result<(), String> {
+        if label.is_empty() || !label.chars().all(|c| c.is_alphanumeric() || c == '_')
+           || label.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(format!("line {}: invalid label {:?}", line_number, label));
+        }
+        if Operand::parse(label, OperandKind::Register).is_ok() || OPCODES.iter().any(|x| x.name == label) {
+            return Err(format!("line {}: label {:?} is a register or an opcode", line_number, label));
+        }
+        if self.labels.insert(label.to_string(), self.instructions.len()).is_some() {
+            return Err(format!("line {}: label {:?} is defined twice", line_number, label));
+        }
+        Ok(())
+    }
+}
+
+const REGISTERS: [char; 5] = ['x', 'a', 'b', 'c', 'd'];
+const X: usize = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OperandKind {
+    // A register name or a number.
+    Value,
+    Register,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    Register(usize),
+    Immediate(i64),
+}
+
+impl Operand {
+    fn parse(text: &str, kind: OperandKind) -> Result<Operand, String> {
+        let register = REGISTERS.iter()
+                                .position(|name| text.len() == 1 && text.starts_with(*name));
+        match (register, kind) {
+            (Some(register), _) => Ok(Operand::Register(register)),
+            (None, OperandKind::Value) => text.parse::<i64>()
+                                              .map(Operand::Immediate)
+                                              .map_err(|_| format!("{:?} is neither a number nor a register", text)),
+            (None, OperandKind::Register) => Err(format!("{:?} is not a register, expected one of {:?}",
+                                                         text, REGISTERS)),
+        }
+    }
+
+    fn value(&self, registers: &[i64]) -> i64 {
+        match self {
+            Operand::Register(register) => registers[*register],
+            Operand::Immediate(value) => *value,
+        }
+    }
+
+    fn register(&self) -> usize {
+        match self {
+            Operand::Register(register) => *register,
+            Operand::Immediate(_) => panic!("Operand was validated to be a register"),
+        }
+    }
+}
+
+// Runs once the last cycle of the instruction is over and returns the jump relative to
+// the current instruction, 1 being the next one.
+type Effect = fn(&mut [i64], &[Operand]) -> i64;
+
+struct Opcode {
+    name: &'static str,
+    cycles: u64,
+    operands: &'static [OperandKind],
+    effect: Effect,
+}
+
+// Registers are named by REGISTERS, jumps are relative to the jumping instruction.
+// Arithmetic wraps around like 64 bit hardware registers do.
+const OPCODES: &[Opcode] = &[
+    Opcode { name: "noop", cycles: 1, operands: &[], effect: |_, _| 1 },
+    Opcode { name: "addx", cycles: 2, operands: &[OperandKind::Value], effect: |registers, operands| {
+        registers[X] = registers[X].wrapping_add(operands[0].value(registers));
+        1
+    } },
+    Opcode { name: "mulx", cycles: 2, operands: &[OperandKind::Value], effect: |registers, operands| {
+        registers[X] = registers[X].wrapping_mul(operands[0].value(registers));
+        1
+    } },
+    Opcode { name: "set", cycles: 1, operands: &[OperandKind::Register, OperandKind::Value], effect: |registers, operands| {
+        registers[operands[0].register()] = operands[1].value(registers);
+        1
+    } },
+    Opcode { name: "jmp", cycles: 1, operands: &[OperandKind::Value], effect: |registers, operands| {
+        operands[0].value(registers)
+    } },
+    Opcode { name: "jnz", cycles: 2, operands: &[OperandKind::Value, OperandKind::Value], effect: |registers, operands| {
+        if operands[0].value(registers) != 0 { operands[1].value(registers) } else { 1 }
+    } },
+];
+
+#[derive(Clone)]
+struct Instruction {
+    opcode: &'static Opcode,
+    operands: Vec<Operand>,
+}
+
+impl Instruction {
+    fn parse(line: &str) -> Result<Instruction, String> {
+        let mut words = line.split_whitespace();
+        let name = words.next().unwrap_or("");
+        let opcode = OPCODES.iter()
+                            .find(|opcode| opcode.name == name)
+                            .ok_or_else(|| format!("unknown opcode {:?}", name))?;
+        let words = words.collect::<Vec<&str>>();
+        if words.len() != opcode.operands.len() {
+            return Err(format!("{} expects {} operands but got {}", name, opcode.operands.len(), words.len()));
+        }
+        Ok(Instruction {
+            opcode,
+            operands: words.iter()
+                           .zip(opcode.operands.iter())
+                           .map(|(word, kind)| Operand::parse(word, *kind))
+                           .collect::<Result<Vec<Operand>, String>>()?,
+        })
+    }
+}
+
+// Same text format parse() reads.
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.opcode.name)?;
+        for operand in self.operands.iter() {
+            match operand {
+                Operand::Register(register) => write!(f, " {}", REGISTERS[*register])?,
+                Operand::Immediate(value) => write!(f, " {}", value)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+// Once the program counter leaves the program the CPU keeps idling with unchanged registers.
 #[derive(Debug, Clone)]
-enum Instruction {
-    Noop { cycle_length: u64 },
-    Addx { cycle_length: u64, argument: i64 }
+struct Cpu {
+    program: Vec<Instruction>,
+    registers: [i64; REGISTERS.len()],
+    pc: usize,
+    cycle: u64,
+    // Cycles the current instruction has already spent.
+    busy: u64,
+}
+
+impl Cpu {
+    fn new(program: Vec<Instruction>) -> Self {
+        let mut registers = [0; REGISTERS.len()];
+        registers[X] = 1;
+        Cpu {
+            program,
+            registers,
+            pc: 0,
+            cycle: 1,
+            busy: 0,
+        }
+    }
+
+    fn x(&self) -> i64 {
+        self.registers[X]
+    }
+
+    fn halted(&self) -> bool {
+        self.pc >= self.program.len()
+    }
+
+    // Finishes the current cycle, registers hold the values for the next one afterwards.
+    fn tick(&mut self) {
+        self.cycle += 1;
+        if self.halted() {
+            return;
+        }
+        let instruction = &self.program[self.pc];
+        self.busy += 1;
+        if self.busy < instruction.opcode.cycles {
+            return;
+        }
+        self.busy = 0;
+        let jump = (instruction.opcode.effect)(&mut self.registers, &instruction.operands);
+        self.pc = match self.pc.checked_add_signed(jump as isize) {
+            Some(pc) => pc,
+            None => self.program.len(),
+        };
+    }
+}
+
+// Pauses a run, either at a given cycle or whenever a register takes a given value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Breakpoint {
+    Cycle(u64),
+    Register(usize, i64),
+}
+
+impl Breakpoint {
+    // "cycle=N" or "<register>=V", for example "x=7".
+    fn parse(text: &str) -> Result<Breakpoint, String> {
+        let (name, value) = text.split_once('=')
+                                .ok_or_else(|| format!("breakpoint {:?} is not of the form name=value", text))?;
+        if name == "cycle" {
+            return value.parse::<u64>()
+                        .map(Breakpoint::Cycle)
+                        .map_err(|e| format!("invalid cycle {:?}: {}", value, e));
+        }
+        let register = match Operand::parse(name, OperandKind::Register)? {
+            Operand::Register(register) => register,
+            Operand::Immediate(_) => unreachable!(),
+        };
+        value.parse::<i64>()
+             .map(|value| Breakpoint::Register(register, value))
+             .map_err(|e| format!("invalid value {:?}: {}", value, e))
+    }
+
+    // A register breakpoint only fires when the value changes to the watched one.
+    fn hit(&self, cpu: &Cpu, previous: Option<&[i64]>) -> bool {
+        match self {
+            Breakpoint::Cycle(cycle) => cpu.cycle == *cycle,
+            Breakpoint::Register(register, value) =>
+                cpu.registers[*register] == *value
+                && previous.is_none_or(|previous| previous[*register] != *value),
+        }
+    }
+}
+
+impl std::fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Breakpoint::Cycle(cycle) => write!(f, "cycle={}", cycle),
+            Breakpoint::Register(register, value) => write!(f, "{}={}", REGISTERS[*register], value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Crt {
+    width: usize,
+    height: usize,
+    // Pixels lit around X, the middle one being at X.
+    sprite_width: usize,
+}
+
+impl Default for Crt {
+    fn default() -> Self {
+        Crt {
+            width: 40,
+            height: 6,
+            sprite_width: 3,
+        }
+    }
+}
+
+impl Crt {
+    fn sprite_covers(&self, x: i64, column: usize) -> bool {
+        let start = x.saturating_sub((self.sprite_width as i64 - 1) / 2);
+        (start..start.saturating_add(self.sprite_width as i64)).contains(&(column as i64))
+    }
 }
 
 #[derive(Debug, Clone)]
 struct VideoSystem {
-    instructions: Vec<Instruction>,
-    current_clock: u64,
-    current_instruction_start: u64,
-    pc: usize,
-    register: i64,
+    cpu: Cpu,
+    crt: Crt,
 }
 
 impl VideoSystem {
-    fn new(instructions: Vec<Instruction>) -> Self {
+    fn new(program: Vec<Instruction>, crt: Crt) -> Self {
         VideoSystem {
-            instructions,
-            current_clock: 1,
-            current_instruction_start: 1,
-            pc: 0,
-            register: 1,
+            cpu: Cpu::new(program),
+            crt,
         }
     }
 
-    fn solve1(&mut self) -> i64 {
-        let mut register_values: Vec<i64> = Vec::new();
+    // Calls `observe` at the start of every cycle until the program ends or `max_cycles`
+    // cycles have passed.
+    fn run<F: FnMut(&Cpu)>(&mut self, max_cycles: u64, mut observe: F) {
+        while !self.cpu.halted() && self.cpu.cycle <= max_cycles {
+            observe(&self.cpu);
+            self.cpu.tick();
+        }
+    }
 
-        loop {
-            self.step();
+    fn write_trace<W: Write>(&mut self, out: W, max_cycles: u64) -> io::Result<()> {
+        let mut out = BufWriter::new(out);
+        writeln!(out, "cycle,pc,instruction,{}",
+                 REGISTERS.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(","))?;
+        let mut result = Ok(());
+        self.run(max_cycles, |cpu| {
+            if result.is_ok() {
+                result = writeln!(out, "{},{},{:?},{}",
+                                  cpu.cycle, cpu.pc, cpu.program[cpu.pc],
+                                  cpu.registers.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(","));
+            }
+        });
+        result?;
+        out.flush()
+    }
 
-            if self.current_clock == 20
-               || (self.current_clock >= 60
-                    && (((self.current_clock - 60) % 40) == 0)) {
-                register_values.push(self.register * (self.current_clock as i64));
+    fn debug(&mut self, breakpoints: &[Breakpoint], max_cycles: u64) {
+        let mut previous: Option<[i64; REGISTERS.len()]> = None;
+        self.run(max_cycles, |cpu| {
+            for breakpoint in breakpoints.iter().filter(|x| x.hit(cpu, previous.as_ref().map(|x| &x[..]))) {
+                println!("breakpoint {} hit during cycle {} at pc {} ({:?}) with {}",
+                         breakpoint, cpu.cycle, cpu.pc, cpu.program[cpu.pc],
+                         REGISTERS.iter()
+                                  .zip(cpu.registers.iter())
+                                  .map(|(name, value)| format!("{}={}", name, value))
+                                  .collect::<Vec<String>>()
+                                  .join(" "));
             }
+            previous = Some(cpu.registers);
+        });
+    }
+
+    // Sums cycle times X over the given cycles.
+    fn solve1(&mut self, samples: &[u64]) -> i64 {
+        let last = samples.iter().copied().max().unwrap_or(0);
+        let mut signal_strength: i64 = 0;
 
-            if register_values.len() >= 6 {
+        loop {
+            if samples.contains(&self.cpu.cycle) {
+                signal_strength = signal_strength.wrapping_add(self.cpu.x().wrapping_mul(self.cpu.cycle as i64));
+            }
+            if self.cpu.cycle >= last {
                 break;
             }
+            self.cpu.tick();
         }
 
-        register_values.iter()
-                       .sum()
+        signal_strength
     }
 
-    fn solve2(&mut self) {
-        for _ in 0..6 {
-            for i in 0..40 {
-                print!("{}", if ((self.register-1)..(self.register+2)).contains(&(i as i64)) {
-                    '#'
-                } else {
-                    '.'
-                });
-                self.step();
-
+    fn solve2(&mut self) -> Screen {
+        let mut screen = Screen::new(self.crt.width, self.crt.height);
+        for y in 0..screen.height {
+            for x in 0..screen.width {
+                if self.crt.sprite_covers(self.cpu.x(), x) {
+                    screen.light(x, y);
+                }
+                self.cpu.tick();
             }
-            print!("\n")
         }
+        screen
     }
+}
 
-    fn next_instruction(&mut self) {
-        self.pc = self.pc + 1;
-        self.current_instruction_start = self.current_clock + 1;
+// Finds a noop/addx program that draws `target`. X only changes after an addx, which keeps
+// it for both of its cycles, so this works backwards which X values can still finish the
+// picture from every cycle, then walks forwards along them starting from X = 1.
+fn draw_program(target: &Screen, crt: &Crt) -> Result<Vec<Instruction>, String> {
+    let cycles = target.width * target.height;
+    let low = -(crt.sprite_width as i64) - 1;
+    let high = (target.width + crt.sprite_width) as i64 + 1;
+    let index = |x: i64| (x - low) as usize;
+    let fits = |cycle: usize, x: i64| {
+        cycle >= cycles
+        || crt.sprite_covers(x, cycle % target.width) == target.is_lit(cycle % target.width, cycle / target.width)
+    };
+
+    // can_finish[cycle][index(x)]: an instruction starting at that cycle with that X can
+    // lead to the rest of the picture.
+    let mut can_finish = vec![vec![true; index(high) + 1]; cycles + 2];
+    for cycle in (0..cycles).rev() {
+        let can_jump = can_finish[cycle + 2].iter().any(|x| *x);
+        for x in low..=high {
+            can_finish[cycle][index(x)] = fits(cycle, x)
+                                          && (can_finish[cycle + 1][index(x)] || (fits(cycle + 1, x) && can_jump));
+        }
+    }
+    if !can_finish[0][index(1)] {
+        return Err(format!("no program can draw this picture with a sprite {} pixels wide", crt.sprite_width));
     }
 
-    fn instruction_done(&self, cycle_length: &u64) -> bool {
-        self.current_instruction_start + cycle_length <= self.current_clock
+    let mut program: Vec<Instruction> = Vec::new();
+    let mut x: i64 = 1;
+    let mut cycle = 0;
+    while cycle < cycles {
+        if can_finish[cycle + 1][index(x)] {
+            program.push(Instruction::parse("noop")?);
+            cycle += 1;
+        } else {
+            let next = (low..=high).filter(|next| can_finish[cycle + 2][index(*next)])
+                                   .min_by_key(|next| (next - x).abs())
+                                   .ok_or_else(|| format!("no X value fits cycle {}", cycle + 2))?;
+            program.push(Instruction::parse(&format!("addx {}", next - x))?);
+            x = next;
+            cycle += 2;
+        }
     }
 
-    fn step(&mut self) {
-        let current_instruction = self.instructions.get(self.pc).unwrap();
+    if VideoSystem::new(program.clone(), *crt).solve2() != *target {
+        return Err(String::from("the generated program draws a different picture"));
+    }
+    Ok(program)
+}
 
-        match current_instruction {
-            Noop { cycle_length} => {
-                if self.instruction_done(cycle_length) {
-                    self.next_instruction();
-                }
-            },
-            Addx { cycle_length, argument} => {
-                if self.instruction_done(cycle_length) {
-                    self.register = self.register + argument;
-                    self.next_instruction();
+const GLYPH_WIDTH: usize = 4;
+const GLYPH_HEIGHT: usize = 6;
+// Glyphs are separated by one blank column.
+const GLYPH_SPACING: usize = GLYPH_WIDTH + 1;
+
+// The capital letters the puzzle is known to draw; the others never show up.
+const FONT: [(char, [&str; GLYPH_HEIGHT]); 18] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', ["#...", "#...", ".#.#", "..#.", "..#.", "..#."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Screen {
+    width: usize,
+    height: usize,
+    pixels: Vec<bool>,
+}
+
+impl Screen {
+    fn new(width: usize, height: usize) -> Self {
+        Screen {
+            width,
+            height,
+            pixels: vec![false; width * height],
+        }
+    }
+
+    // Rows of '#' and '.', as Display writes them.
+    fn parse(lines: &[String]) -> Result<Screen, String> {
+        let rows: Vec<(usize, &str)> = lines.iter()
+                                            .enumerate()
+                                            .map(|(i, line)| (i + 1, line.trim()))
+                                            .filter(|(_, line)| !line.is_empty())
+                                            .collect();
+        let width = rows.first().map_or(0, |(_, row)| row.chars().count());
+        let mut screen = Screen::new(width, rows.len());
+        for (y, (line_number, row)) in rows.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err(format!("line {}: row has {} pixels but the first row has {}",
+                                   line_number, row.chars().count(), width));
+            }
+            for (x, pixel) in row.chars().enumerate() {
+                match pixel {
+                    '#' => screen.light(x, y),
+                    '.' => {},
+                    _ => return Err(format!("line {}: unexpected pixel {:?}", line_number, pixel)),
                 }
             }
         }
+        Ok(screen)
+    }
 
-        self.current_clock += 1;
+    fn light(&mut self, x: usize, y: usize) {
+        self.pixels[y * self.width + x] = true;
     }
-}
 
+    fn is_lit(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.pixels[y * self.width + x]
+    }
 
+    // The glyph whose left edge is at column `left`, one string per row.
+    fn glyph(&self, left: usize) -> [String; GLYPH_HEIGHT] {
+        std::array::from_fn(|y| {
+            (left..left + GLYPH_WIDTH).map(|x| if self.is_lit(x, y) { '#' } else { '.' })
+                                      .collect()
+        })
+    }
 
+    // Reads the screen as a row of letters, or lists every glyph missing from FONT.
+    fn read_letters(&self) -> Result<String, String> {
+        let mut letters = String::new();
+        let mut unknown: Vec<String> = Vec::new();
+        for (i, left) in (0..self.width).step_by(GLYPH_SPACING).enumerate() {
+            let glyph = self.glyph(left);
+            match FONT.iter().find(|(_, rows)| glyph.iter().zip(rows.iter()).all(|(l, r)| l == r)) {
+                Some((letter, _)) => letters.push(*letter),
+                None => unknown.push(format!("glyph {} at column {} is unknown:\n{}",
+                                             i + 1, left, glyph.join("\n"))),
+            }
+        }
+        if unknown.is_empty() {
+            Ok(letters)
+        } else {
+            Err(unknown.join("\n"))
+        }
+    }
+}
 
+impl std::fmt::Display for Screen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for y in 0..self.height {
+            let row: String = (0..self.width).map(|x| if self.is_lit(x, y) { '#' } else { '.' })
+                                             .collect();
+            writeln!(f, "{}", row)?;
+        }
+        Ok(())
+    }
+}
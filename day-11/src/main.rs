@@ -1,217 +1,524 @@
-use std::collections::HashMap;
 use std::fs::File;
 use std::io::{self, BufReader, BufRead};
 use std::env;
-use crate::Operand::Constant;
+use num_bigint::BigUint;
 
 fn main() ->  io::Result<()> {
     let args: Vec<String> = env::args().collect();
     let monkeys = read_input(&args[1])?;
 
-    println!("{:?} is the level of monkey business after 20 rounds of stuff-slinging simian shenanigans",
-             solve(monkeys.clone()));
+    let mut relief: Option<String> = None;
+    let mut rounds: Option<usize> = None;
+    let mut options = args.iter().skip(2);
+    while let Some(option) = options.next() {
+        let mut value = || options.next()
+                                  .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+                                                                format!("{} expects a value", option)));
+        match option.as_str() {
+            "--relief" => relief = Some(value()?.clone()),
+            "--rounds" => rounds = Some(value()?.parse::<usize>()
+                                                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                           format!("unknown option {}", option))),
+        }
+    }
+
+    if relief.is_none() && rounds.is_none() {
+        println!("{:?} is the level of monkey business after 20 rounds of stuff-slinging simian shenanigans",
+                 monkey_business(&monkeys, "divide:3", 20)?);
+
+        println!("{:?} is the level of monkey business after 10000 rounds",
+                 monkey_business(&monkeys, "lcm", 10000)?);
+    } else {
+        let relief = relief.unwrap_or(String::from("divide:3"));
+        let rounds = rounds.unwrap_or(20);
+        println!("{:?} is the level of monkey business after {} rounds with relief {}",
+                 monkey_business(&monkeys, &relief, rounds)?, rounds, relief);
+    }
 
     Ok(())
 }
 
-fn solve(mut monkeys: Vec<Monkey>) -> usize {
-   for _ in 0..20 {
+// Product of the two highest inspection counts.
+fn monkey_business(monkeys: &[Monkey], relief: &str, rounds: usize) -> io::Result<usize> {
+    let inspections = match relief.split_once(':') {
+        None if relief == "none" => play(monkeys, &NoRelief, rounds),
+        None if relief == "lcm" => ModuloRelief::new(monkeys).and_then(|relief| play(monkeys, &relief, rounds)),
+        Some(("divide", divisor)) => {
+            let divisor = divisor.parse::<u64>()
+                                 .ok()
+                                 .filter(|x| *x > 0)
+                                 .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+                                                               format!("invalid divisor {:?}", divisor)))?;
+            play(monkeys, &DivideRelief { divisor }, rounds)
+        },
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                       format!("unknown relief {}, expected divide:K, lcm or none", relief))),
+    }.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let mut inspections = inspections;
+    inspections.sort();
+    Ok(inspections.iter().rev().take(2).product())
+}
 
-       for i in 0..monkeys.len() {
-            let appensions = monkeys.get_mut(i).unwrap().do_round();
-            for x in appensions.iter() {
-                let mut items = (x.1).clone();
-                monkeys.get_mut(*(x.0)).unwrap().extend_items(&mut items);
+// Returns how many items every monkey inspected.
+fn play<R: Relief>(monkeys: &[Monkey], relief: &R, rounds: usize) -> Result<Vec<usize>, String> {
+    for (i, monkey) in monkeys.iter().enumerate() {
+        relief.supports(&monkey.operation)
+              .map_err(|e| format!("monkey {}: {} cannot be used with relief {}: {}",
+                                   i, monkey.operation, relief.name(), e))?;
+    }
+    let mut items: Vec<Vec<R::Worry>> =
+        monkeys.iter()
+               .map(|monkey| monkey.items.iter().map(|x| relief.start(*x)).collect())
+               .collect();
+    let mut inspections = vec![0; monkeys.len()];
+
+    for round in 0..rounds {
+        for (i, monkey) in monkeys.iter().enumerate() {
+            for worry in std::mem::take(&mut items[i]) {
+                let worry = monkey.operation
+                                  .evaluate(relief, &worry)
+                                  .map(|x| relief.relieve(x))
+                                  .ok_or_else(|| format!("monkey {} cannot compute {} in round {} with relief {}, \
+                                                          the worry level overflows, goes below zero or is divided by zero",
+                                                         i, monkey.operation, round + 1, relief.name()))?;
+                let target = if relief.is_divisible(&worry, monkey.test) {
+                    monkey.test_true
+                } else {
+                    monkey.test_false
+                };
+                items[target].push(worry);
+                inspections[i] += 1;
             }
         }
-   }
+    }
 
-    let mut inspections =
-        monkeys.iter()
-               .map(|monkey|monkey.nr_of_inspections)
-               .collect::<Vec<usize>>();
+    Ok(inspections)
+}
 
-    inspections.sort();
+// How worry levels are represented, how operations are computed on them and how they are
+// kept in check after every inspection.
+trait Relief {
+    type Worry: Clone;
+
+    fn name(&self) -> String;
 
-    inspections.iter().rev().take(2).fold(1, |prod, val| prod * val)
+    // Turns starting items as well as constants in operations into worry levels.
+    fn start(&self, worry: u64) -> Self::Worry;
 
+    // The arithmetic of operations, None if the result cannot be represented.
+    fn add(&self, left: &Self::Worry, right: &Self::Worry) -> Option<Self::Worry>;
+    fn subtract(&self, left: &Self::Worry, right: &Self::Worry) -> Option<Self::Worry>;
+    fn multiply(&self, left: &Self::Worry, right: &Self::Worry) -> Option<Self::Worry>;
+    fn divide(&self, left: &Self::Worry, right: &Self::Worry) -> Option<Self::Worry>;
+
+    // Applied once the monkey is done with its operation.
+    fn relieve(&self, worry: Self::Worry) -> Self::Worry;
+
+    fn is_divisible(&self, worry: &Self::Worry, test: u64) -> bool;
+
+    fn supports(&self, _expression: &Expression) -> Result<(), String> {
+        Ok(())
+    }
 }
 
-fn read_input(filename: &String) -> io::Result<Vec<Monkey>> {
-    let file_in = File::open(filename)?;
+// Part one: the worry level is divided by `divisor`, rounding down.
+struct DivideRelief {
+    divisor: u64,
+}
+
+impl Relief for DivideRelief {
+    type Worry = u64;
 
+    fn name(&self) -> String {
+        format!("divide:{}", self.divisor)
+    }
 
-    let raw_lines =
-        BufReader::new(file_in).lines()
-                  .map(|line|line.unwrap())
-                  .collect::<Vec<String>>();
+    fn start(&self, worry: u64) -> u64 {
+        worry
+    }
 
-    let mut lines = raw_lines.iter();
+    fn add(&self, left: &u64, right: &u64) -> Option<u64> {
+        left.checked_add(*right)
+    }
 
-    let mut monkeys: Vec<Monkey> = Vec::new();
+    fn subtract(&self, left: &u64, right: &u64) -> Option<u64> {
+        left.checked_sub(*right)
+    }
+
+    fn multiply(&self, left: &u64, right: &u64) -> Option<u64> {
+        left.checked_mul(*right)
+    }
 
-    loop {
-        let line = lines.next();
+    fn divide(&self, left: &u64, right: &u64) -> Option<u64> {
+        left.checked_div(*right)
+    }
 
-        if line.is_none() {
-            break;
+    fn relieve(&self, worry: u64) -> u64 {
+        worry / self.divisor
+    }
+
+    fn is_divisible(&self, worry: &u64, test: u64) -> bool {
+        worry.is_multiple_of(test)
+    }
+}
+
+// Part two: no relief, but every test only looks at the remainder, so worry levels can be
+// kept modulo the least common multiple of all tests.
+struct ModuloRelief {
+    modulus: u64,
+}
+
+impl ModuloRelief {
+    fn new(monkeys: &[Monkey]) -> Result<Self, String> {
+        let modulus = monkeys.iter()
+                             .try_fold(1u64, |lcm, monkey| (lcm / gcd(lcm, monkey.test)).checked_mul(monkey.test))
+                             .ok_or("the least common multiple of all tests does not fit in 64 bits")?;
+        Ok(ModuloRelief {
+            modulus,
+        })
+    }
+}
+
+impl Relief for ModuloRelief {
+    type Worry = u64;
+
+    fn name(&self) -> String {
+        String::from("lcm")
+    }
+
+    fn start(&self, worry: u64) -> u64 {
+        worry % self.modulus
+    }
+
+    fn add(&self, left: &u64, right: &u64) -> Option<u64> {
+        Some(((*left as u128 + *right as u128) % self.modulus as u128) as u64)
+    }
+
+    // Remainders cannot tell whether the exact worry level goes below zero, supports()
+    // keeps subtraction out.
+    fn subtract(&self, _left: &u64, _right: &u64) -> Option<u64> {
+        None
+    }
+
+    fn multiply(&self, left: &u64, right: &u64) -> Option<u64> {
+        Some(((*left as u128 * *right as u128) % self.modulus as u128) as u64)
+    }
+
+    fn divide(&self, _left: &u64, _right: &u64) -> Option<u64> {
+        None
+    }
+
+    fn relieve(&self, worry: u64) -> u64 {
+        worry
+    }
+
+    fn is_divisible(&self, worry: &u64, test: u64) -> bool {
+        worry.is_multiple_of(test)
+    }
+
+    // Remainders of a quotient do not follow from the remainders of its operands, and those
+    // of a difference do not show whether it went below zero.
+    fn supports(&self, expression: &Expression) -> Result<(), String> {
+        if expression.any(&|x| matches!(x, Expression::Divide(_, _))) {
+            Err(String::from("division cannot be done on remainders"))
+        } else if expression.any(&|x| matches!(x, Expression::Subtract(_, _))) {
+            Err(String::from("subtraction cannot be done on remainders, it may go below zero unnoticed"))
+        } else {
+            Ok(())
         }
+    }
+}
 
-        if line.unwrap().starts_with("Monkey") {
-            let items
-                = lines.next()
-                       .unwrap()
-                       .chars()
-                       .skip("  Starting items: ".len())
-                       .collect::<String>()
-                       .split(", ")
-                       .map(|x| x.parse::<usize>().unwrap())
-                       .collect::<Vec<usize>>();
-
-            let operation_line =
-                lines.next()
-                     .unwrap()
-                     .chars()
-                     .skip("  Operation: new = ".len())
-                     .collect::<String>();
-
-            let plus_operation = operation_line.contains(" + ");
-            let mut split =
-                if plus_operation {
-                    operation_line.split(" + ")
-                } else {
-                    operation_line.split(" * ")
-                };
-            let left = split.next().map(|x| parse_operand(x)).unwrap();
-            let right = split.next().map(|x| parse_operand(x)).unwrap();
-            let operator =
-                if plus_operation {
-                    Operator::Plus
-                }  else {
-                    Operator::Times
-                };
+// No relief and exact worry levels, which grow quickly.
+struct NoRelief;
 
-            let test =
-                lines.next()
-                     .unwrap()
-                     .chars()
-                     .skip("  Test: divisible by ".len())
-                     .collect::<String>()
-                     .parse::<usize>().unwrap();
-            let test_true =
-                lines.next()
-                    .unwrap()
-                    .chars()
-                    .skip("    If true: throw to monkey ".len())
-                    .collect::<String>()
-                    .parse::<usize>().unwrap();
-            let test_false =
-                lines.next()
-                    .unwrap()
-                    .chars()
-                    .skip("    If false: throw to monkey ".len())
-                    .collect::<String>()
-                    .parse::<usize>().unwrap();
-
-            let monkey =
-                Monkey {
-                items,
-                left,
-                right,
-                operator,
-                test,
-                test_true,
-                test_false,
-                nr_of_inspections: 0
-            };
+impl Relief for NoRelief {
+    type Worry = BigUint;
+
+    fn name(&self) -> String {
+        String::from("none")
+    }
+
+    fn start(&self, worry: u64) -> BigUint {
+        BigUint::from(worry)
+    }
+
+    fn add(&self, left: &BigUint, right: &BigUint) -> Option<BigUint> {
+        Some(left + right)
+    }
+
+    fn subtract(&self, left: &BigUint, right: &BigUint) -> Option<BigUint> {
+        if left >= right { Some(left - right) } else { None }
+    }
+
+    fn multiply(&self, left: &BigUint, right: &BigUint) -> Option<BigUint> {
+        Some(left * right)
+    }
+
+    fn divide(&self, left: &BigUint, right: &BigUint) -> Option<BigUint> {
+        if *right == BigUint::ZERO { None } else { Some(left / right) }
+    }
+
+    fn relieve(&self, worry: BigUint) -> BigUint {
+        worry
+    }
+
+    fn is_divisible(&self, worry: &BigUint, test: u64) -> bool {
+        (worry % test) == BigUint::ZERO
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+fn read_input(filename: &String) -> io::Result<Vec<Monkey>> {
+    let file_in = File::open(filename)?;
+    let lines = BufReader::new(file_in)
+                    .lines()
+                    .collect::<io::Result<Vec<String>>>()?;
+    parse_monkeys(&lines).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn parse_monkeys(lines: &[String]) -> Result<Vec<Monkey>, String> {
+    let mut lines = lines.iter().enumerate().map(|(i, line)| (i + 1, line.trim()));
+    let mut monkeys: Vec<Monkey> = Vec::new();
 
-            monkeys.push(monkey);
+    while let Some((line_number, line)) = lines.next() {
+        if line.is_empty() {
+            continue;
         }
+        if !line.starts_with("Monkey") {
+            return Err(format!("line {}: expected a monkey but got {:?}", line_number, line));
+        }
+        let mut field = |prefix: &str| match lines.next() {
+            Some((line_number, line)) => line.strip_prefix(prefix)
+                                             .map(|x| (line_number, x.trim()))
+                                             .ok_or_else(|| format!("line {}: expected {:?}", line_number, prefix)),
+            None => Err(format!("line {}: monkey ends before {:?}", line_number, prefix)),
+        };
+        let (items_line, items) = field("Starting items:")?;
+        let items = items.split(',')
+                         .map(|x| x.trim())
+                         .filter(|x| !x.is_empty())
+                         .map(|x| x.parse::<u64>().map_err(|e| format!("line {}: invalid item {:?}: {}", items_line, x, e)))
+                         .collect::<Result<Vec<u64>, String>>()?;
+        let (operation_line, operation) = field("Operation: new =")?;
+        let operation = Expression::parse(operation).map_err(|e| format!("line {}: {}", operation_line, e))?;
+        let (test_line, test) = field("Test: divisible by")?;
+        let test = parse_field((test_line, test))?;
+        if test == 0 {
+            return Err(format!("line {}: monkeys cannot test for divisibility by 0", test_line));
+        }
+        let test_true = parse_field(field("If true: throw to monkey")?)?;
+        let test_false = parse_field(field("If false: throw to monkey")?)?;
+
+        monkeys.push(Monkey {
+            items,
+            operation,
+            test,
+            test_true: test_true as usize,
+            test_false: test_false as usize,
+        });
     }
 
+    if let Some((i, _)) = monkeys.iter()
+                                 .enumerate()
+                                 .find(|(_, monkey)| monkey.test_true.max(monkey.test_false) >= monkeys.len()) {
+        return Err(format!("monkey {} throws to a monkey that does not exist", i));
+    }
     Ok(monkeys)
 }
 
-fn parse_operand(operand: &str) -> Operand {
-    if operand == "old" {
-        Operand::Old
-    } else {
-        Constant(operand.parse::<usize>().unwrap())
-    }
+fn parse_field((line_number, text): (usize, &str)) -> Result<u64, String> {
+    text.parse::<u64>().map_err(|e| format!("line {}: invalid number {:?}: {}", line_number, text, e))
 }
 
 #[derive(Debug, Clone)]
 struct Monkey {
-    items: Vec<usize>,
-    left: Operand,
-    right: Operand,
-    operator: Operator,
-    test: usize,
+    items: Vec<u64>,
+    operation: Expression,
+    test: u64,
     test_true: usize,
     test_false: usize,
-    nr_of_inspections: usize
 }
 
-impl Monkey {
-    fn do_round(&mut self) -> HashMap<usize, Vec<usize>> {
-        let mut rvalue: HashMap<usize, Vec<usize>> = HashMap::new();
-        rvalue.insert(self.test_true,Vec::new());
-        rvalue.insert(self.test_false,Vec::new());
+// The right hand side of "new = ...", for example "(old + 3) * old ^ 2".
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expression {
+    Old,
+    Constant(u64),
+    Add(Box<Expression>, Box<Expression>),
+    Subtract(Box<Expression>, Box<Expression>),
+    Multiply(Box<Expression>, Box<Expression>),
+    Divide(Box<Expression>, Box<Expression>),
+    Power(Box<Expression>, u32),
+}
 
-        for item in &self.items {
-            let new_worry_level = self.new_worry_level(*item);
-            rvalue.get_mut(&self.find_target(new_worry_level)).unwrap().push(new_worry_level);
-            self.nr_of_inspections += 1;
+impl Expression {
+    fn parse(text: &str) -> Result<Expression, String> {
+        let tokens = tokenize(text)?;
+        let mut parser = ExpressionParser { tokens: &tokens, position: 0 };
+        let expression = parser.sum()?;
+        match parser.peek() {
+            None => Ok(expression),
+            Some(token) => Err(format!("unexpected {:?} in operation {:?}", token, text)),
         }
-
-        self.items = Vec::new();
-        rvalue
     }
 
-    fn new_worry_level(&self, current_worry_level: usize) -> usize {
-        self.operator.evaluate(&self.left, &self.right,current_worry_level )
+    fn evaluate<R: Relief>(&self, relief: &R, old: &R::Worry) -> Option<R::Worry> {
+        match self {
+            Expression::Old => Some(old.clone()),
+            Expression::Constant(value) => Some(relief.start(*value)),
+            Expression::Add(left, right) =>
+                relief.add(&left.evaluate(relief, old)?, &right.evaluate(relief, old)?),
+            Expression::Subtract(left, right) =>
+                relief.subtract(&left.evaluate(relief, old)?, &right.evaluate(relief, old)?),
+            Expression::Multiply(left, right) =>
+                relief.multiply(&left.evaluate(relief, old)?, &right.evaluate(relief, old)?),
+            Expression::Divide(left, right) =>
+                relief.divide(&left.evaluate(relief, old)?, &right.evaluate(relief, old)?),
+            Expression::Power(base, exponent) => {
+                // Square and multiply.
+                let mut base = base.evaluate(relief, old)?;
+                let mut result = relief.start(1);
+                let mut exponent = *exponent;
+                while exponent > 0 {
+                    if exponent & 1 == 1 {
+                        result = relief.multiply(&result, &base)?;
+                    }
+                    exponent >>= 1;
+                    if exponent > 0 {
+                        base = relief.multiply(&base, &base)?;
+                    }
+                }
+                Some(result)
+            },
+        }
     }
 
-    fn find_target(&self, new_worry_level: usize) -> usize {
-        if new_worry_level % self.test == 0 {
-            self.test_true
-        } else {
-            self.test_false
+    fn any(&self, predicate: &dyn Fn(&Expression) -> bool) -> bool {
+        predicate(self) || match self {
+            Expression::Old | Expression::Constant(_) => false,
+            Expression::Add(left, right)
+            | Expression::Subtract(left, right)
+            | Expression::Multiply(left, right)
+            | Expression::Divide(left, right) => left.any(predicate) || right.any(predicate),
+            Expression::Power(base, _) => base.any(predicate),
         }
     }
+}
 
-    fn extend_items(&mut self, items: &mut Vec<usize>) {
-        self.items.append(items);
+impl std::fmt::Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Old => write!(f, "old"),
+            Expression::Constant(value) => write!(f, "{}", value),
+            Expression::Add(left, right) => write!(f, "({} + {})", left, right),
+            Expression::Subtract(left, right) => write!(f, "({} - {})", left, right),
+            Expression::Multiply(left, right) => write!(f, "({} * {})", left, right),
+            Expression::Divide(left, right) => write!(f, "({} / {})", left, right),
+            Expression::Power(base, exponent) => write!(f, "{} ^ {}", base, exponent),
+        }
     }
 }
 
+fn tokenize(text: &str) -> Result<Vec<String>, String> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            _ if c.is_whitespace() => {},
+            '+' | '-' | '*' | '/' | '^' | '(' | ')' => tokens.push(c.to_string()),
+            _ if c.is_alphanumeric() => {
+                let mut word = c.to_string();
+                while let Some(next) = chars.next_if(|x| x.is_alphanumeric()) {
+                    word.push(next);
+                }
+                tokens.push(word);
+            },
+            _ => return Err(format!("unexpected {:?} in operation {:?}", c, text)),
+        }
+    }
+    Ok(tokens)
+}
 
-#[derive(Debug, Clone)]
-enum Operand {
-    Constant(usize),
-    Old,
+// Recursive descent with the usual precedence: ^ binds tighter than * and /, which bind
+// tighter than + and -.
+struct ExpressionParser<'a> {
+    tokens: &'a [String],
+    position: usize,
 }
 
-impl Operand {
-    fn evaluate(&self, old: usize)  -> usize {
-        *(match self {
-            Operand::Constant(value) => value,
-            Operand::Old => &old
-        })
+impl ExpressionParser<'_> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(|x| x.as_str())
     }
-}
 
-#[derive(Debug, Clone)]
-enum Operator {
-    Plus,
-    Times,
-}
+    fn next(&mut self) -> Option<&str> {
+        self.position += 1;
+        self.tokens.get(self.position - 1).map(|x| x.as_str())
+    }
 
-impl Operator {
-    fn evaluate(&self, left: &Operand, right: &Operand, old: usize) -> usize {
-        match self {
-            Operator::Plus => left.evaluate(old) + right.evaluate(old),
-            Operator::Times => left.evaluate(old) * right.evaluate(old)
+    fn sum(&mut self) -> Result<Expression, String> {
+        let mut expression = self.product()?;
+        while let Some(operator) = self.peek().filter(|x| *x == "+" || *x == "-") {
+            let subtract = operator == "-";
+            self.position += 1;
+            let right = Box::new(self.product()?);
+            expression = if subtract {
+                Expression::Subtract(Box::new(expression), right)
+            } else {
+                Expression::Add(Box::new(expression), right)
+            };
+        }
+        Ok(expression)
+    }
+
+    fn product(&mut self) -> Result<Expression, String> {
+        let mut expression = self.power()?;
+        while let Some(operator) = self.peek().filter(|x| *x == "*" || *x == "/") {
+            let divide = operator == "/";
+            self.position += 1;
+            let right = Box::new(self.power()?);
+            expression = if divide {
+                Expression::Divide(Box::new(expression), right)
+            } else {
+                Expression::Multiply(Box::new(expression), right)
+            };
+        }
+        Ok(expression)
+    }
+
+    // Exponents have to be plain numbers.
+    fn power(&mut self) -> Result<Expression, String> {
+        let mut expression = self.atom()?;
+        while self.peek() == Some("^") {
+            self.position += 1;
+            let exponent = self.next().ok_or("operation ends after ^")?;
+            let exponent = exponent.parse::<u32>()
+                                   .map_err(|_| format!("exponent {:?} is not a number", exponent))?;
+            expression = Expression::Power(Box::new(expression), exponent);
+        }
+        Ok(expression)
+    }
+
+    fn atom(&mut self) -> Result<Expression, String> {
+        match self.next() {
+            Some("old") => Ok(Expression::Old),
+            Some("(") => {
+                let expression = self.sum()?;
+                match self.next() {
+                    Some(")") => Ok(expression),
+                    _ => Err(String::from("missing )")),
+                }
+            },
+            Some(token) => token.parse::<u64>()
+                                .map(Expression::Constant)
+                                .map_err(|_| format!("{:?} is neither old, a number nor (", token)),
+            None => Err(String::from("operation ends too early")),
         }
     }
 }
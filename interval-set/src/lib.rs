@@ -0,0 +1,273 @@
+use std::cmp::{max, min};
+use std::iter::FromIterator;
+use std::ops::{Add, Range, Sub};
+
+/// A set of values stored as sorted, disjoint, half-open ranges.
+///
+/// Overlapping and touching ranges are merged on insert, so `0..3` and `3..5`
+/// end up as the single range `0..5`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IntervalSet<T> {
+    ranges: Vec<Range<T>>,
+}
+
+impl<T: Copy + Ord> IntervalSet<T> {
+    pub fn new() -> Self {
+        IntervalSet {
+            ranges: Vec::new(),
+        }
+    }
+
+    pub fn from_range(range: Range<T>) -> Self {
+        let mut set = IntervalSet::new();
+        set.insert(range);
+        set
+    }
+
+    pub fn insert(&mut self, range: Range<T>) {
+        if range.start >= range.end {
+            return;
+        }
+        // Everything in first..last touches the new range and is merged into it.
+        let first = self.ranges.partition_point(|x| x.end < range.start);
+        let last = self.ranges.partition_point(|x| x.start <= range.end);
+        let merged = if first < last {
+            min(range.start, self.ranges[first].start)..max(range.end, self.ranges[last - 1].end)
+        } else {
+            range
+        };
+        self.ranges.splice(first..last, std::iter::once(merged));
+    }
+
+    pub fn ranges(&self) -> &[Range<T>] {
+        &self.ranges
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Range<T>> {
+        self.ranges.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        let index = self.ranges.partition_point(|x| x.end <= *value);
+        self.ranges.get(index).is_some_and(|x| x.contains(value))
+    }
+
+    /// True when every value of `range` is in the set.
+    pub fn covers(&self, range: &Range<T>) -> bool {
+        range.start >= range.end
+            || self.ranges.iter().any(|x| x.start <= range.start && range.end <= x.end)
+    }
+
+    /// True when at least one value of `range` is in the set.
+    pub fn overlaps(&self, range: &Range<T>) -> bool {
+        self.ranges.iter().any(|x| x.start < range.end && range.start < x.end)
+    }
+
+    /// The smallest single range containing the whole set.
+    pub fn hull(&self) -> Option<Range<T>> {
+        match (self.ranges.first(), self.ranges.last()) {
+            (Some(first), Some(last)) => Some(first.start..last.end),
+            _ => None,
+        }
+    }
+
+    pub fn union(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut set = self.clone();
+        other.iter().for_each(|x| set.insert(x.clone()));
+        set
+    }
+
+    pub fn intersection(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut ranges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let l = &self.ranges[i];
+            let r = &other.ranges[j];
+            let start = max(l.start, r.start);
+            let end = min(l.end, r.end);
+            if start < end {
+                ranges.push(start..end);
+            }
+            if l.end < r.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        IntervalSet {
+            ranges,
+        }
+    }
+
+    pub fn difference(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        match self.hull() {
+            Some(hull) => self.intersection(&other.complement(hull)),
+            None => IntervalSet::new(),
+        }
+    }
+
+    /// Everything in `bounds` that is not in the set.
+    pub fn complement(&self, bounds: Range<T>) -> IntervalSet<T> {
+        let mut ranges = Vec::new();
+        let mut start = bounds.start;
+        for range in self.ranges.iter() {
+            if range.end <= start {
+                continue;
+            }
+            if range.start >= bounds.end {
+                break;
+            }
+            if start < range.start {
+                ranges.push(start..range.start);
+            }
+            start = range.end;
+        }
+        if start < bounds.end {
+            ranges.push(start..bounds.end);
+        }
+        IntervalSet {
+            ranges,
+        }
+    }
+
+    /// The holes between consecutive ranges, in ascending order.
+    pub fn gaps(&self) -> impl Iterator<Item = Range<T>> + '_ {
+        self.ranges.windows(2).map(|x| x[0].end..x[1].start)
+    }
+}
+
+impl<T: Copy + Ord + Add<Output = T> + Sub<Output = T> + Default> IntervalSet<T> {
+    pub fn total_length(&self) -> T {
+        self.ranges.iter().fold(T::default(), |sum, x| sum + (x.end - x.start))
+    }
+}
+
+impl<T: Copy + Ord> FromIterator<Range<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Range<T>>>(iter: I) -> Self {
+        let mut set = IntervalSet::new();
+        iter.into_iter().for_each(|x| set.insert(x));
+        set
+    }
+}
+
+impl<T: Copy + Ord> Extend<Range<T>> for IntervalSet<T> {
+    fn extend<I: IntoIterator<Item = Range<T>>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|x| self.insert(x));
+    }
+}
+
+impl<'a, T> IntoIterator for &'a IntervalSet<T> {
+    type Item = &'a Range<T>;
+    type IntoIter = std::slice::Iter<'a, Range<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.ranges.iter()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
+mod tests {
+    use super::IntervalSet;
+
+    fn set(ranges: &[std::ops::Range<i64>]) -> IntervalSet<i64> {
+        ranges.iter().cloned().collect()
+    }
+
+    #[test]
+    fn insert_merges_touching_ranges() {
+        assert_eq!(set(&[0..3, 3..5]).ranges(), &[0..5]);
+        assert_eq!(set(&[3..5, 0..3]).ranges(), &[0..5]);
+    }
+
+    #[test]
+    fn insert_merges_overlapping_ranges() {
+        assert_eq!(set(&[0..4, 2..6]).ranges(), &[0..6]);
+        assert_eq!(set(&[0..2, 4..6, 8..10, 1..9]).ranges(), &[0..10]);
+        assert_eq!(set(&[0..10, 2..3]).ranges(), &[0..10]);
+    }
+
+    #[test]
+    fn insert_keeps_disjoint_ranges_sorted() {
+        assert_eq!(set(&[6..8, 0..2, 3..4]).ranges(), &[0..2, 3..4, 6..8]);
+    }
+
+    #[test]
+    fn empty_ranges_are_ignored() {
+        let empty = set(&[3..3, std::ops::Range { start: 5, end: 2 }]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.total_length(), 0);
+        assert_eq!(set(&[0..2, 2..2, 4..4]).ranges(), &[0..2]);
+        assert!(IntervalSet::<i64>::new().gaps().next().is_none());
+        assert!(empty.covers(&(7..7)));
+    }
+
+    #[test]
+    fn contains_respects_half_open_ends() {
+        let s = set(&[0..3, 5..7]);
+        assert!(s.contains(&0));
+        assert!(s.contains(&2));
+        assert!(!s.contains(&3));
+        assert!(!s.contains(&4));
+        assert!(s.contains(&5));
+        assert!(!s.contains(&7));
+    }
+
+    #[test]
+    fn intersection_of_interleaved_sets() {
+        let left = set(&[0..5, 10..15]);
+        let right = set(&[3..12, 14..20]);
+        assert_eq!(left.intersection(&right).ranges(), &[3..5, 10..12, 14..15]);
+        assert!(left.intersection(&set(&[5..10])).is_empty());
+    }
+
+    #[test]
+    fn difference_with_a_gap_in_the_middle() {
+        let whole = set(&[0..10]);
+        assert_eq!(whole.difference(&set(&[4..6])).ranges(), &[0..4, 6..10]);
+        assert_eq!(whole.difference(&set(&[0..10])).ranges(), &[]);
+        assert_eq!(whole.difference(&set(&[-5..2, 8..20])).ranges(), &[2..8]);
+    }
+
+    #[test]
+    fn complement_at_the_bounds() {
+        let s = set(&[0..2, 5..10]);
+        assert_eq!(s.complement(0..10).ranges(), &[2..5]);
+        assert_eq!(s.complement(-3..12).ranges(), &[-3..0, 2..5, 10..12]);
+        assert_eq!(s.complement(1..6).ranges(), &[2..5]);
+        assert_eq!(IntervalSet::new().complement(0..4).ranges(), &[0..4]);
+        assert!(set(&[0..4]).complement(0..4).is_empty());
+    }
+
+    #[test]
+    fn gaps_between_ranges() {
+        assert_eq!(set(&[0..2, 5..7, 9..10]).gaps().collect::<Vec<_>>(), vec![2..5, 7..9]);
+        assert!(set(&[0..10]).gaps().next().is_none());
+    }
+
+    #[test]
+    fn total_length_and_hull() {
+        let s = set(&[0..3, 3..5, 10..12]);
+        assert_eq!(s.total_length(), 7);
+        assert_eq!(s.hull(), Some(0..12));
+        assert_eq!(IntervalSet::<i64>::new().hull(), None);
+    }
+
+    #[test]
+    fn union_covers_and_overlaps() {
+        let s = set(&[0..3]).union(&set(&[3..6, 8..9]));
+        assert_eq!(s.ranges(), &[0..6, 8..9]);
+        assert!(s.covers(&(1..6)));
+        assert!(!s.covers(&(5..9)));
+        assert!(s.overlaps(&(5..7)));
+        assert!(!s.overlaps(&(6..8)));
+    }
+}